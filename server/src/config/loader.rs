@@ -0,0 +1,236 @@
+//
+//  media-savant-api
+//  config/loader.rs
+//
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+/// Every problem found while loading configuration, reported together.
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, error) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "- {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// Resolves each setting from, in increasing precedence: the TOML file, the environment, and a
+/// file named by `<ENV>_FILE` (Docker secrets). Errors are collected rather than returned, so
+/// one run reports every invalid setting.
+pub struct Loader {
+    file: Table,
+    file_path: Option<PathBuf>,
+    used: HashSet<String>,
+    errors: Vec<String>,
+}
+
+impl Loader {
+    /// Reads `path` if given. A missing file is only an error when `required`, so the default
+    /// location can simply be absent.
+    pub fn new(path: &Path, required: bool) -> Self {
+        let mut loader = Self {
+            file: Table::new(),
+            file_path: None,
+            used: HashSet::new(),
+            errors: Vec::new(),
+        };
+
+        match std::fs::read_to_string(path) {
+            Ok(contents) => match contents.parse::<Table>() {
+                Ok(file) => {
+                    loader.file = file;
+                    loader.file_path = Some(path.to_path_buf());
+                }
+                Err(err) => loader.error(format!(
+                    "{}: {}",
+                    path.display(),
+                    err.to_string().trim_end()
+                )),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => {}
+            Err(err) => loader.error(format!("Cannot read {}: {err}", path.display())),
+        }
+        loader
+    }
+
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.errors.push(message.into());
+    }
+
+    /// Raw value of a setting, known in the file as `path` (`section.key`) and in the
+    /// environment as `env`.
+    fn raw(&mut self, path: &str, env: &str) -> Option<String> {
+        self.used.insert(path.to_string());
+
+        let file_var = format!("{env}_FILE");
+        if let Some(secret_path) = std::env::var(&file_var)
+            .ok()
+            .filter(|path| !path.is_empty())
+        {
+            return match std::fs::read_to_string(&secret_path) {
+                Ok(value) => Some(value.trim_end_matches(['\r', '\n']).to_string()),
+                Err(err) => {
+                    self.error(format!("{file_var}: cannot read {secret_path}: {err}"));
+                    None
+                }
+            };
+        }
+        // Empty variables, as left by `.env` templates, fall through to the file.
+        if let Some(value) = std::env::var(env).ok().filter(|value| !value.is_empty()) {
+            return Some(value);
+        }
+
+        match self.lookup(path)? {
+            Value::String(value) => Some(value.clone()),
+            Value::Integer(value) => Some(value.to_string()),
+            Value::Float(value) => Some(value.to_string()),
+            Value::Boolean(value) => Some(value.to_string()),
+            _ => {
+                self.error(format!("{path} must be a string, number or boolean"));
+                None
+            }
+        }
+    }
+
+    /// A table or array from the file, for settings with more structure than one value.
+    pub fn file_value(&mut self, path: &str) -> Option<Value> {
+        self.used.insert(path.to_string());
+        self.lookup(path).cloned()
+    }
+
+    fn lookup(&self, path: &str) -> Option<&Value> {
+        let (sections, key) = path.rsplit_once('.').unwrap_or(("", path));
+        let mut table = &self.file;
+        for section in sections.split('.').filter(|section| !section.is_empty()) {
+            table = table.get(section)?.as_table()?;
+        }
+        table.get(key)
+    }
+
+    pub fn string(&mut self, path: &str, env: &str, default: &str) -> String {
+        self.raw(path, env).unwrap_or_else(|| default.to_string())
+    }
+
+    /// An optional string; an empty value counts as unset.
+    pub fn optional(&mut self, path: &str, env: &str) -> Option<String> {
+        self.raw(path, env).filter(|value| !value.is_empty())
+    }
+
+    pub fn required(&mut self, path: &str, env: &str) -> String {
+        self.raw(path, env).unwrap_or_else(|| {
+            self.error(format!("{env} (or {path} in the config file) must be set"));
+            String::new()
+        })
+    }
+
+    /// A list, given in the environment as comma separated values and in the file as an array
+    /// of strings. Blank entries are dropped.
+    pub fn list(&mut self, path: &str, env: &str) -> Option<Vec<String>> {
+        let values: Vec<String> = match std::env::var(env).ok().filter(|list| !list.is_empty()) {
+            Some(list) => list.split(',').map(str::to_string).collect(),
+            None => match self.file_value(path)? {
+                Value::Array(entries) => entries
+                    .into_iter()
+                    .filter_map(|entry| match entry {
+                        Value::String(value) => Some(value),
+                        _ => {
+                            self.error(format!("{path} must only contain strings"));
+                            None
+                        }
+                    })
+                    .collect(),
+                _ => {
+                    self.error(format!("{path} must be an array"));
+                    return None;
+                }
+            },
+        };
+        Some(
+            values
+                .iter()
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+                .collect(),
+        )
+    }
+
+    pub fn parse<T: FromStr + Default>(
+        &mut self,
+        path: &str,
+        env: &str,
+        default: &str,
+        expected: &str,
+    ) -> T {
+        self.parse_checked(path, env, default, expected, |_| true)
+    }
+
+    /// Parses a setting and checks it with `valid`; `expected` completes "must be ...".
+    pub fn parse_checked<T: FromStr + Default>(
+        &mut self,
+        path: &str,
+        env: &str,
+        default: &str,
+        expected: &str,
+        valid: impl Fn(&T) -> bool,
+    ) -> T {
+        let raw = self.raw(path, env).unwrap_or_else(|| default.to_string());
+        match raw.trim().parse::<T>() {
+            Ok(value) if valid(&value) => value,
+            _ => {
+                self.error(format!("{env} / {path} must be {expected}, got {raw:?}"));
+                T::default()
+            }
+        }
+    }
+
+    /// Fails with every collected error, including keys in the file that nothing read.
+    pub fn finish(mut self) -> Result<(), ConfigErrors> {
+        let mut unknown = Vec::new();
+        collect_unknown(&self.file, "", &self.used, &mut unknown);
+        for path in unknown {
+            self.error(format!("Unknown setting {path} in the config file"));
+        }
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(self.errors))
+        }
+    }
+}
+
+fn collect_unknown(table: &Table, prefix: &str, used: &HashSet<String>, unknown: &mut Vec<String>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        if used.contains(&path) {
+            continue;
+        }
+        match value {
+            Value::Table(table) => collect_unknown(table, &path, used, unknown),
+            _ => unknown.push(path),
+        }
+    }
+}
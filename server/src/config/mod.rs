@@ -3,14 +3,47 @@
 //  config/mod.rs
 //
 
-use anyhow::{Context, Result};
+mod loader;
+mod reload;
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::redact::Secret;
+use crate::wol;
+
+pub use loader::ConfigErrors;
+use loader::Loader;
+pub use reload::{watch, ReloadStatus};
+
+/// Read when neither `--config` nor `CONFIG_FILE` names another file; may be absent.
+pub const DEFAULT_CONFIG_FILE: &str = "/etc/media-savant/config.toml";
 
 #[derive(Debug, Clone)]
 pub struct Config {
+    /// The TOML file settings were read from, if one existed.
+    pub file: Option<PathBuf>,
     pub app: AppConfig,
+    pub server: ServerConfig,
+    /// Set when the API terminates TLS itself rather than behind a proxy.
+    pub tls: Option<TlsConfig>,
     pub redis: RedisConfig,
     pub auth: AuthConfig,
+    pub cors: CorsConfig,
     pub rate_limit: RateLimitConfig,
+    pub images: ImageCacheConfig,
+    pub metadata_cache: MetadataCacheConfig,
+    pub proxy: ProxyConfig,
+    pub upstream: UpstreamConfig,
+    pub telemetry: TelemetryConfig,
+    pub metrics: MetricsConfig,
+    pub jellyfin: JellyfinConfig,
+    pub health: HealthConfig,
+    pub shutdown: ShutdownConfig,
 }
 
 #[derive(Debug, Clone)]
@@ -21,9 +54,38 @@ pub struct AppConfig {
     pub client_version: String,
 }
 
+/// Listener and connection tuning for the HTTP server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub workers: usize,
+    /// Every address to listen on, e.g. `0.0.0.0` and `[::]` for IPv4 and IPv6.
+    pub binds: Vec<SocketAddr>,
+    /// Idle time before a kept-alive connection is closed; zero disables keep-alive.
+    pub keep_alive: Duration,
+    /// Time a client has to send the request head.
+    pub client_request_timeout: Duration,
+    /// Time a client has to acknowledge a connection shutdown.
+    pub client_disconnect_timeout: Duration,
+    /// Concurrent connections per worker.
+    pub max_connections: usize,
+    pub json_limit_bytes: usize,
+    /// Largest request body the Jellyfin proxy forwards, e.g. an uploaded image.
+    pub upload_limit_bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// PEM certificate chain and private key, re-read on every reload.
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// Plain-HTTP port that redirects to HTTPS, on the same addresses as `server.bind`.
+    pub redirect_port: Option<u16>,
+}
+
 #[derive(Debug, Clone)]
 pub struct RedisConfig {
-    pub url: String,
+    /// May carry a password, e.g. `redis://:password@redis:6379`.
+    pub url: Secret<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -33,76 +95,904 @@ pub struct AuthConfig {
 }
 
 #[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API with credentials; empty allows any origin.
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RateLimitConfig {
     pub per_second: u64,
     pub burst: u32,
 }
 
+#[derive(Debug, Clone)]
+pub struct ImageCacheConfig {
+    pub dir: PathBuf,
+    pub max_bytes: u64,
+    /// Re-encode artwork to WebP/AVIF when the browser accepts it.
+    pub transcode: bool,
+    pub webp_quality: f32,
+    pub avif_quality: u8,
+    /// rav1e speed preset, 1 (slowest, smallest) to 10 (fastest).
+    pub avif_speed: u8,
+}
+
+/// TTLs per class of cached Jellyfin metadata; a zero TTL disables that class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataCacheConfig {
+    pub enabled: bool,
+    pub max_entries: usize,
+    pub views_ttl: Duration,
+    pub seasons_ttl: Duration,
+    pub similar_ttl: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// Proxied WebSockets with no traffic in either direction for this long are closed.
+    pub ws_idle_timeout: Duration,
+    pub ws_max_frame_bytes: usize,
+}
+
+/// Timeouts, retries and trust settings for requests to Jellyfin.
+#[derive(Debug, Clone)]
+pub struct UpstreamConfig {
+    pub connect_timeout: Duration,
+    /// API calls, artwork and the generic proxy.
+    pub metadata: UpstreamTimeouts,
+    /// Video streams, which may idle while the client buffers and run for hours.
+    pub streaming: UpstreamTimeouts,
+    /// Extra attempts for metadata GETs that fail to connect, time out or get a 502-504.
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each one after it.
+    pub retry_backoff: Duration,
+    /// PEM certificates trusted in addition to the public roots, e.g. a LAN CA.
+    pub ca_bundle: Option<PathBuf>,
+    /// Consecutive failures that open a server's circuit; zero disables the breaker.
+    pub breaker_threshold: u32,
+    /// How often a server with an open circuit is pinged.
+    pub breaker_probe_interval: Duration,
+    /// How long a server woken with Wake-on-LAN may take before it is reported unavailable.
+    pub wake_timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpstreamTimeouts {
+    /// Longest wait for the next chunk of the response.
+    pub read: Duration,
+    /// Limit for the whole exchange, body included; `None` for no limit.
+    pub total: Option<Duration>,
+}
+
+/// Jellyfin servers this deployment is expected to reach. Users may still sign in to others;
+/// these are the ones readiness checks probe.
+#[derive(Debug, Clone)]
+pub struct JellyfinConfig {
+    pub servers: Vec<JellyfinServerConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JellyfinServerConfig {
+    pub url: String,
+    /// Accept any certificate from this server. Prefer `upstream.ca_bundle` or pinning.
+    #[serde(default)]
+    pub insecure_tls: bool,
+    /// SHA-256 fingerprints of the certificates this server may present; when set, no other
+    /// certificate is accepted, whoever signed it.
+    #[serde(default)]
+    pub pinned_certs: Vec<String>,
+    /// Wake-on-LAN: the server's MAC address, e.g. `aa:bb:cc:dd:ee:ff`.
+    #[serde(default)]
+    pub mac: Option<String>,
+    /// Where to send the magic packet; defaults to `255.255.255.255:9`.
+    #[serde(default)]
+    pub broadcast: Option<String>,
+    /// Parsed from `mac` and `broadcast`.
+    #[serde(skip)]
+    pub wake: Option<WakeTarget>,
+}
+
+/// A sleeping server is woken by broadcasting a magic packet for `mac` to `broadcast`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WakeTarget {
+    pub mac: [u8; 6],
+    pub broadcast: SocketAddr,
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    /// Upper bound for each dependency probe in `/api/health/ready`.
+    pub check_timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ShutdownConfig {
+    /// How long in-flight streams may keep playing after a stop signal before they are cut.
+    pub grace_period: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub log_format: LogFormat,
+    /// OTLP/HTTP collector base URL; spans are only exported when this is set.
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsConfig {
+    /// Bearer token scrapers must send to `/metrics`; the endpoint is open when unset.
+    pub token: Option<Secret<String>>,
+    /// How long the session count is reused before Redis is scanned again.
+    pub sessions_refresh: Duration,
+}
+
 impl Config {
-    pub fn from_env() -> Result<Self> {
-        Ok(Self {
-            app: AppConfig::from_env()?,
-            redis: RedisConfig::from_env()?,
-            auth: AuthConfig::from_env()?,
-            rate_limit: RateLimitConfig::from_env()?,
-        })
+    /// Loads the TOML file at `path`, then applies environment overrides and `*_FILE` secrets.
+    /// `required` makes a missing file an error, for paths the operator named explicitly.
+    pub fn load(path: &Path, required: bool) -> Result<Self, ConfigErrors> {
+        let mut loader = Loader::new(path, required);
+        let app = AppConfig::load(&mut loader);
+        let tls = TlsConfig::load(&mut loader);
+        let config = Self {
+            file: loader.file_path().map(Path::to_path_buf),
+            server: ServerConfig::load(&mut loader, app.port),
+            app,
+            redis: RedisConfig::load(&mut loader),
+            auth: AuthConfig::load(&mut loader, tls.is_some()),
+            tls,
+            cors: CorsConfig::load(&mut loader),
+            rate_limit: RateLimitConfig::load(&mut loader),
+            images: ImageCacheConfig::load(&mut loader),
+            metadata_cache: MetadataCacheConfig::load(&mut loader),
+            proxy: ProxyConfig::load(&mut loader),
+            upstream: UpstreamConfig::load(&mut loader),
+            telemetry: TelemetryConfig::load(&mut loader),
+            metrics: MetricsConfig::load(&mut loader),
+            jellyfin: JellyfinConfig::load(&mut loader),
+            health: HealthConfig::load(&mut loader),
+            shutdown: ShutdownConfig::load(&mut loader),
+        };
+        config.validate(&mut loader);
+        loader.finish()?;
+        Ok(config)
+    }
+
+    /// Settings that differ in `next` but only take effect after a restart, named as in the
+    /// config file.
+    pub fn restart_required(&self, next: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        let mut check = |name, differs| {
+            if differs {
+                changed.push(name);
+            }
+        };
+        check("app.port", self.app.port != next.app.port);
+        check("server", self.server != next.server);
+        check("tls", self.tls != next.tls);
+        check("redis.url", self.redis.url != next.redis.url);
+        check("images.cache_dir", self.images.dir != next.images.dir);
+        check(
+            "images.cache_max_mb",
+            self.images.max_bytes != next.images.max_bytes,
+        );
+        check(
+            "telemetry.log_format",
+            self.telemetry.log_format != next.telemetry.log_format,
+        );
+        check(
+            "telemetry.otlp_endpoint",
+            self.telemetry.otlp_endpoint != next.telemetry.otlp_endpoint,
+        );
+        check(
+            "telemetry.service_name",
+            self.telemetry.service_name != next.telemetry.service_name,
+        );
+        changed
+    }
+
+    /// `next` with every restart-only setting taken from `self`, so the stored config keeps
+    /// describing what is actually running.
+    pub fn with_restart_only_from(&self, mut next: Config) -> Config {
+        next.app.port = self.app.port;
+        next.server = self.server.clone();
+        next.tls = self.tls.clone();
+        next.redis = self.redis.clone();
+        next.images.dir = self.images.dir.clone();
+        next.images.max_bytes = self.images.max_bytes;
+        next.telemetry = self.telemetry.clone();
+        next
+    }
+
+    /// Checks that need more than one setting.
+    fn validate(&self, loader: &mut Loader) {
+        if self.metadata_cache.enabled && self.metadata_cache.max_entries == 0 {
+            loader.error(
+                "METADATA_CACHE_ENABLED is true but METADATA_CACHE_MAX_ENTRIES is 0; \
+                 disable the cache instead",
+            );
+        }
+        let redirect_port = self.tls.as_ref().and_then(|tls| tls.redirect_port);
+        if let Some(port) =
+            redirect_port.filter(|port| self.server.binds.iter().any(|bind| bind.port() == *port))
+        {
+            loader.error(format!(
+                "TLS_REDIRECT_PORT {port} is already used by an HTTPS listener"
+            ));
+        }
+        for (index, server) in self.jellyfin.servers.iter().enumerate() {
+            if self.jellyfin.servers[..index]
+                .iter()
+                .any(|other| other.url == server.url)
+            {
+                loader.error(format!("Jellyfin server {} is listed twice", server.url));
+            }
+        }
     }
 }
 
 impl AppConfig {
-    fn from_env() -> Result<Self> {
-        let port = get_env("APP_PORT")?.parse::<u16>().context("APP_PORT must be a valid port")?;
-        let client_name = get_env_default("JELLYFIN_CLIENT_NAME", "mdia-savant")?;
-        let device_name = get_env_default("JELLYFIN_DEVICE_NAME", "mdia-savant")?;
-        let client_version = get_env_default("JELLYFIN_CLIENT_VERSION", "0.1.0")?;
+    fn load(loader: &mut Loader) -> Self {
+        let port = loader.required("app.port", "APP_PORT");
+        let port = match port.parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => {
+                if !port.is_empty() {
+                    loader.error(format!(
+                        "APP_PORT / app.port must be a valid port, got {port:?}"
+                    ));
+                }
+                0
+            }
+        };
 
-        Ok(Self {
+        Self {
             port,
-            client_name,
-            device_name,
-            client_version,
-        })
+            client_name: loader.string("app.client_name", "JELLYFIN_CLIENT_NAME", "mdia-savant"),
+            device_name: loader.string("app.device_name", "JELLYFIN_DEVICE_NAME", "mdia-savant"),
+            client_version: loader.string("app.client_version", "JELLYFIN_CLIENT_VERSION", "0.1.0"),
+        }
+    }
+}
+
+impl ServerConfig {
+    fn load(loader: &mut Loader, port: u16) -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+        let workers = loader.parse_checked(
+            "server.workers",
+            "SERVER_WORKERS",
+            &cpus.to_string(),
+            "a positive integer",
+            |workers| *workers > 0,
+        );
+
+        // Entries without a port listen on `app.port`.
+        let binds = loader
+            .list("server.bind", "SERVER_BIND")
+            .unwrap_or_else(|| vec!["0.0.0.0".to_string()])
+            .into_iter()
+            .filter_map(|bind| match parse_bind(&bind, port) {
+                Some(addr) => Some(addr),
+                None => {
+                    loader.error(format!(
+                        "SERVER_BIND / server.bind entries must be IP addresses with an optional \
+                         port, got {bind:?}"
+                    ));
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if binds.is_empty() {
+            loader.error("SERVER_BIND / server.bind must list at least one address");
+        }
+
+        let keep_alive_secs: u64 = loader.parse(
+            "server.keep_alive_secs",
+            "SERVER_KEEP_ALIVE_SECS",
+            "5",
+            "an integer",
+        );
+        let request_timeout_ms: u64 = loader.parse(
+            "server.client_request_timeout_ms",
+            "SERVER_CLIENT_REQUEST_TIMEOUT_MS",
+            "5000",
+            "an integer",
+        );
+        let disconnect_timeout_ms: u64 = loader.parse(
+            "server.client_disconnect_timeout_ms",
+            "SERVER_CLIENT_DISCONNECT_TIMEOUT_MS",
+            "1000",
+            "an integer",
+        );
+        let json_limit_kb: usize = loader.parse_checked(
+            "server.json_limit_kb",
+            "SERVER_JSON_LIMIT_KB",
+            "2048",
+            "a positive integer",
+            |kb| *kb > 0,
+        );
+        let upload_limit_mb: usize = loader.parse_checked(
+            "server.upload_limit_mb",
+            "SERVER_UPLOAD_LIMIT_MB",
+            "16",
+            "a positive integer",
+            |mb| *mb > 0,
+        );
+
+        Self {
+            workers,
+            binds,
+            keep_alive: Duration::from_secs(keep_alive_secs),
+            client_request_timeout: Duration::from_millis(request_timeout_ms),
+            client_disconnect_timeout: Duration::from_millis(disconnect_timeout_ms),
+            max_connections: loader.parse_checked(
+                "server.max_connections",
+                "SERVER_MAX_CONNECTIONS",
+                "25000",
+                "a positive integer",
+                |max| *max > 0,
+            ),
+            json_limit_bytes: in_bytes(
+                loader,
+                "SERVER_JSON_LIMIT_KB / server.json_limit_kb",
+                json_limit_kb.checked_mul(1024),
+            ),
+            upload_limit_bytes: in_bytes(
+                loader,
+                "SERVER_UPLOAD_LIMIT_MB / server.upload_limit_mb",
+                upload_limit_mb.checked_mul(1024 * 1024),
+            ),
+        }
+    }
+}
+
+/// A size setting converted to bytes, or an error when the product does not fit.
+fn in_bytes<T: Default>(loader: &mut Loader, setting: &str, bytes: Option<T>) -> T {
+    bytes.unwrap_or_else(|| {
+        loader.error(format!("{setting} is too large"));
+        T::default()
+    })
+}
+
+/// `0.0.0.0`, `[::]:8080` or `::1`; addresses without a port use `default_port`.
+fn parse_bind(bind: &str, default_port: u16) -> Option<SocketAddr> {
+    bind.parse::<SocketAddr>().ok().or_else(|| {
+        bind.trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, default_port))
+    })
+}
+
+impl TlsConfig {
+    /// TLS is on once both paths are given.
+    fn load(loader: &mut Loader) -> Option<Self> {
+        let cert_path = loader.optional("tls.cert_path", "TLS_CERT_PATH");
+        let key_path = loader.optional("tls.key_path", "TLS_KEY_PATH");
+        let redirect_port = loader
+            .optional("tls.redirect_port", "TLS_REDIRECT_PORT")
+            .and_then(|port| match port.parse::<u16>() {
+                Ok(port) if port > 0 => Some(port),
+                _ => {
+                    loader.error(format!(
+                        "TLS_REDIRECT_PORT / tls.redirect_port must be a valid port, got {port:?}"
+                    ));
+                    None
+                }
+            });
+
+        match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => Some(Self {
+                cert_path: PathBuf::from(cert_path),
+                key_path: PathBuf::from(key_path),
+                redirect_port,
+            }),
+            (None, None) => {
+                if redirect_port.is_some() {
+                    loader.error("TLS_REDIRECT_PORT is set, but TLS is not configured");
+                }
+                None
+            }
+            _ => {
+                loader.error("TLS_CERT_PATH and TLS_KEY_PATH must be set together");
+                None
+            }
+        }
     }
 }
 
 impl RedisConfig {
-    fn from_env() -> Result<Self> {
-        let url = get_env_default("REDIS_URL", "redis://redis:6379")?;
-        Ok(Self { url })
+    fn load(loader: &mut Loader) -> Self {
+        Self {
+            url: Secret::new(loader.string("redis.url", "REDIS_URL", "redis://redis:6379")),
+        }
     }
 }
 
 impl AuthConfig {
-    fn from_env() -> Result<Self> {
-        let cookie_name = get_env_default("SESSION_COOKIE_NAME", "ms_session")?;
-        let cookie_secure = get_env_default("SESSION_COOKIE_SECURE", "false")?
-            .parse::<bool>()
-            .context("SESSION_COOKIE_SECURE must be true/false")?;
+    /// Cookies default to `Secure` whenever the API itself serves HTTPS.
+    fn load(loader: &mut Loader, tls: bool) -> Self {
+        let cookie_name = loader.string("auth.cookie_name", "SESSION_COOKIE_NAME", "ms_session");
+        if cookie_name.is_empty() {
+            loader.error("SESSION_COOKIE_NAME / auth.cookie_name must not be empty");
+        }
 
-        Ok(Self {
+        Self {
             cookie_name,
-            cookie_secure,
-        })
+            cookie_secure: loader.parse(
+                "auth.cookie_secure",
+                "SESSION_COOKIE_SECURE",
+                if tls { "true" } else { "false" },
+                "true/false",
+            ),
+        }
+    }
+}
+
+impl CorsConfig {
+    fn load(loader: &mut Loader) -> Self {
+        let origins = loader
+            .list("cors.allowed_origins", "CORS_ALLOWED_ORIGINS")
+            .unwrap_or_default();
+
+        // Browsers send `Origin` without a trailing slash or path.
+        let allowed_origins = origins
+            .iter()
+            .map(|origin| origin.trim_end_matches('/'))
+            .map(|origin| {
+                if !origin.starts_with("http://") && !origin.starts_with("https://") {
+                    loader.error(format!(
+                        "CORS origins must be http(s) origins, got {origin:?}"
+                    ));
+                }
+                origin.to_string()
+            })
+            .collect();
+        Self { allowed_origins }
+    }
+
+    pub fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins.is_empty()
+            || self
+                .allowed_origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
     }
 }
 
 impl RateLimitConfig {
-    fn from_env() -> Result<Self> {
-        let per_second = get_env_default("RATE_LIMIT_PER_SECOND", "100")?
-            .parse::<u64>()
-            .context("RATE_LIMIT_PER_SECOND must be an integer")?;
-        let burst = get_env_default("RATE_LIMIT_BURST", "200")?
-            .parse::<u32>()
-            .context("RATE_LIMIT_BURST must be an integer")?;
+    fn load(loader: &mut Loader) -> Self {
+        Self {
+            per_second: loader.parse_checked(
+                "rate_limit.per_second",
+                "RATE_LIMIT_PER_SECOND",
+                "100",
+                "a positive integer",
+                |value| *value > 0,
+            ),
+            burst: loader.parse_checked(
+                "rate_limit.burst",
+                "RATE_LIMIT_BURST",
+                "200",
+                "a positive integer",
+                |value| *value > 0,
+            ),
+        }
+    }
+}
+
+impl ImageCacheConfig {
+    fn load(loader: &mut Loader) -> Self {
+        let dir = loader.string(
+            "images.cache_dir",
+            "IMAGE_CACHE_DIR",
+            "/var/cache/media-savant/images",
+        );
+        let max_mb: u64 = loader.parse(
+            "images.cache_max_mb",
+            "IMAGE_CACHE_MAX_MB",
+            "1024",
+            "an integer",
+        );
+
+        Self {
+            dir: PathBuf::from(dir),
+            max_bytes: in_bytes(
+                loader,
+                "IMAGE_CACHE_MAX_MB / images.cache_max_mb",
+                max_mb.checked_mul(1024 * 1024),
+            ),
+            transcode: loader.parse("images.transcode", "IMAGE_TRANSCODE", "true", "true/false"),
+            webp_quality: loader.parse_checked(
+                "images.webp_quality",
+                "IMAGE_WEBP_QUALITY",
+                "80",
+                "a number between 0 and 100",
+                |quality| (0.0..=100.0).contains(quality),
+            ),
+            avif_quality: loader.parse_checked(
+                "images.avif_quality",
+                "IMAGE_AVIF_QUALITY",
+                "60",
+                "an integer between 1 and 100",
+                |quality| (1..=100).contains(quality),
+            ),
+            avif_speed: loader.parse_checked(
+                "images.avif_speed",
+                "IMAGE_AVIF_SPEED",
+                "8",
+                "an integer between 1 and 10",
+                |speed| (1..=10).contains(speed),
+            ),
+        }
+    }
+}
+
+impl MetadataCacheConfig {
+    fn load(loader: &mut Loader) -> Self {
+        let mut ttl = |path: &str, env: &str, default: &str| -> Duration {
+            Duration::from_secs(loader.parse(path, env, default, "an integer"))
+        };
+        let views_ttl = ttl(
+            "metadata_cache.views_ttl_secs",
+            "METADATA_CACHE_VIEWS_TTL_SECS",
+            "300",
+        );
+        let seasons_ttl = ttl(
+            "metadata_cache.seasons_ttl_secs",
+            "METADATA_CACHE_SEASONS_TTL_SECS",
+            "600",
+        );
+        let similar_ttl = ttl(
+            "metadata_cache.similar_ttl_secs",
+            "METADATA_CACHE_SIMILAR_TTL_SECS",
+            "3600",
+        );
+
+        Self {
+            enabled: loader.parse(
+                "metadata_cache.enabled",
+                "METADATA_CACHE_ENABLED",
+                "true",
+                "true/false",
+            ),
+            max_entries: loader.parse(
+                "metadata_cache.max_entries",
+                "METADATA_CACHE_MAX_ENTRIES",
+                "10000",
+                "an integer",
+            ),
+            views_ttl,
+            seasons_ttl,
+            similar_ttl,
+        }
+    }
+}
+
+impl ProxyConfig {
+    fn load(loader: &mut Loader) -> Self {
+        let idle_secs: u64 = loader.parse_checked(
+            "proxy.ws_idle_timeout_secs",
+            "PROXY_WS_IDLE_TIMEOUT_SECS",
+            "120",
+            "a positive integer",
+            |secs| *secs > 0,
+        );
+        let max_frame_kb: usize = loader.parse_checked(
+            "proxy.ws_max_frame_kb",
+            "PROXY_WS_MAX_FRAME_KB",
+            "1024",
+            "a positive integer",
+            |kb| *kb > 0,
+        );
 
-        Ok(Self { per_second, burst })
+        Self {
+            ws_idle_timeout: Duration::from_secs(idle_secs),
+            ws_max_frame_bytes: in_bytes(
+                loader,
+                "PROXY_WS_MAX_FRAME_KB / proxy.ws_max_frame_kb",
+                max_frame_kb.checked_mul(1024),
+            ),
+        }
     }
 }
 
-fn get_env(key: &str) -> Result<String> {
-    std::env::var(key).with_context(|| format!("{key} must be set"))
+impl UpstreamConfig {
+    fn load(loader: &mut Loader) -> Self {
+        let mut millis = |path: &str, env: &str, default: &str| -> u64 {
+            loader.parse_checked(path, env, default, "a positive integer", |ms| *ms > 0)
+        };
+        let connect_ms = millis(
+            "upstream.connect_timeout_ms",
+            "UPSTREAM_CONNECT_TIMEOUT_MS",
+            "5000",
+        );
+        let metadata_read_ms = millis(
+            "upstream.metadata_read_timeout_ms",
+            "UPSTREAM_METADATA_READ_TIMEOUT_MS",
+            "15000",
+        );
+        let metadata_total_ms = millis(
+            "upstream.metadata_timeout_ms",
+            "UPSTREAM_METADATA_TIMEOUT_MS",
+            "30000",
+        );
+        let stream_read_ms = millis(
+            "upstream.stream_read_timeout_ms",
+            "UPSTREAM_STREAM_READ_TIMEOUT_MS",
+            "60000",
+        );
+        let stream_total_ms: u64 = loader.parse(
+            "upstream.stream_timeout_ms",
+            "UPSTREAM_STREAM_TIMEOUT_MS",
+            "0",
+            "an integer",
+        );
+        let retries = loader.parse_checked(
+            "upstream.retries",
+            "UPSTREAM_RETRIES",
+            "2",
+            "an integer up to 10",
+            |retries| *retries <= 10,
+        );
+        let backoff_ms: u64 = loader.parse(
+            "upstream.retry_backoff_ms",
+            "UPSTREAM_RETRY_BACKOFF_MS",
+            "250",
+            "an integer",
+        );
+        let breaker_threshold = loader.parse(
+            "upstream.breaker_failures",
+            "UPSTREAM_BREAKER_FAILURES",
+            "5",
+            "an integer",
+        );
+        let probe_ms: u64 = loader.parse_checked(
+            "upstream.breaker_probe_interval_ms",
+            "UPSTREAM_BREAKER_PROBE_INTERVAL_MS",
+            "5000",
+            "a positive integer",
+            |ms| *ms > 0,
+        );
+        let wake_secs: u64 = loader.parse(
+            "upstream.wake_timeout_secs",
+            "UPSTREAM_WAKE_TIMEOUT_SECS",
+            "120",
+            "an integer",
+        );
+
+        Self {
+            connect_timeout: Duration::from_millis(connect_ms),
+            metadata: UpstreamTimeouts {
+                read: Duration::from_millis(metadata_read_ms),
+                total: Some(Duration::from_millis(metadata_total_ms)),
+            },
+            streaming: UpstreamTimeouts {
+                read: Duration::from_millis(stream_read_ms),
+                total: (stream_total_ms > 0).then(|| Duration::from_millis(stream_total_ms)),
+            },
+            retries,
+            retry_backoff: Duration::from_millis(backoff_ms),
+            ca_bundle: loader
+                .optional("upstream.ca_bundle", "UPSTREAM_CA_BUNDLE")
+                .map(PathBuf::from),
+            breaker_threshold,
+            breaker_probe_interval: Duration::from_millis(probe_ms),
+            wake_timeout: Duration::from_secs(wake_secs),
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TelemetryConfig {
+    fn load(loader: &mut Loader) -> Self {
+        Self {
+            log_format: loader.parse("telemetry.log_format", "LOG_FORMAT", "text", "text/json"),
+            otlp_endpoint: loader
+                .optional("telemetry.otlp_endpoint", "OTEL_EXPORTER_OTLP_ENDPOINT"),
+            service_name: loader.string(
+                "telemetry.service_name",
+                "OTEL_SERVICE_NAME",
+                "media-savant-api",
+            ),
+        }
+    }
+}
+
+impl JellyfinConfig {
+    /// `JELLYFIN_SERVERS` is a comma separated list of URLs; the file takes `[[jellyfin.servers]]`
+    /// tables or plain URL strings.
+    fn load(loader: &mut Loader) -> Self {
+        let file_servers = loader.file_value("jellyfin.servers");
+        let servers = match std::env::var("JELLYFIN_SERVERS")
+            .ok()
+            .filter(|list| !list.is_empty())
+        {
+            Some(list) => list
+                .split(',')
+                .filter(|url| !url.trim().is_empty())
+                .map(|url| JellyfinServerConfig {
+                    url: url.trim().to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            None => match file_servers {
+                None => Vec::new(),
+                Some(toml::Value::Array(entries)) => entries
+                    .into_iter()
+                    .filter_map(|entry| match entry {
+                        toml::Value::String(url) => Some(JellyfinServerConfig {
+                            url,
+                            ..Default::default()
+                        }),
+                        entry => entry
+                            .try_into::<JellyfinServerConfig>()
+                            .map_err(|err| {
+                                loader.error(format!(
+                                    "jellyfin.servers: {}",
+                                    err.to_string().trim_end()
+                                ))
+                            })
+                            .ok(),
+                    })
+                    .collect(),
+                Some(_) => {
+                    loader.error("jellyfin.servers must be an array");
+                    Vec::new()
+                }
+            },
+        };
+
+        let servers = servers
+            .into_iter()
+            .map(|mut server| {
+                server.url = server.url.trim_end_matches('/').to_string();
+                if !server.url.starts_with("http://") && !server.url.starts_with("https://") {
+                    loader.error(format!(
+                        "Jellyfin servers must be http(s) URLs, got {:?}",
+                        server.url
+                    ));
+                }
+                server.pinned_certs = server
+                    .pinned_certs
+                    .iter()
+                    .filter_map(|pin| {
+                        let pin = normalize_fingerprint(pin);
+                        if pin.is_none() {
+                            loader.error(format!(
+                                "Pinned certificates for {} must be SHA-256 fingerprints in hex",
+                                server.url
+                            ));
+                        }
+                        pin
+                    })
+                    .collect();
+                if server.insecure_tls && !server.pinned_certs.is_empty() {
+                    loader.error(format!(
+                        "{} sets both insecure_tls and pinned_certs; choose one",
+                        server.url
+                    ));
+                }
+                server.wake = WakeTarget::load(loader, &server);
+                server
+            })
+            .collect();
+        Self { servers }
+    }
+}
+
+impl WakeTarget {
+    /// Checks `mac` before `broadcast`, so a server with a bad MAC reports just that.
+    fn load(loader: &mut Loader, server: &JellyfinServerConfig) -> Option<Self> {
+        let Some(mac) = &server.mac else {
+            if server.broadcast.is_some() {
+                loader.error(format!("{} sets broadcast without mac", server.url));
+            }
+            return None;
+        };
+        let Some(mac) = wol::parse_mac(mac) else {
+            loader.error(format!(
+                "MAC address for {} must look like aa:bb:cc:dd:ee:ff, got {mac:?}",
+                server.url
+            ));
+            return None;
+        };
+        let broadcast = match &server.broadcast {
+            None => wol::DEFAULT_BROADCAST,
+            Some(broadcast) => match parse_bind(broadcast, wol::DEFAULT_PORT) {
+                Some(broadcast) => broadcast,
+                None => {
+                    loader.error(format!(
+                        "Broadcast address for {} must be an IP address with an optional port, \
+                         got {broadcast:?}",
+                        server.url
+                    ));
+                    return None;
+                }
+            },
+        };
+        Some(Self { mac, broadcast })
+    }
+}
+
+/// Lowercase hex without separators, from the `AB:CD:...` form `openssl x509 -fingerprint`
+/// prints or plain hex.
+fn normalize_fingerprint(pin: &str) -> Option<String> {
+    let hex: String = pin
+        .chars()
+        .filter(|c| *c != ':')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    (hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())).then_some(hex)
+}
+
+impl MetricsConfig {
+    fn load(loader: &mut Loader) -> Self {
+        let refresh_secs: u64 = loader.parse_checked(
+            "metrics.sessions_refresh_secs",
+            "METRICS_SESSIONS_REFRESH_SECS",
+            "60",
+            "a positive integer",
+            |secs| *secs > 0,
+        );
+
+        Self {
+            token: loader
+                .optional("metrics.token", "METRICS_TOKEN")
+                .map(Secret::new),
+            sessions_refresh: Duration::from_secs(refresh_secs),
+        }
+    }
 }
 
-fn get_env_default(key: &str, default: &str) -> Result<String> {
-    Ok(std::env::var(key).unwrap_or_else(|_| default.to_string()))
+impl HealthConfig {
+    fn load(loader: &mut Loader) -> Self {
+        let timeout_ms: u64 = loader.parse_checked(
+            "health.check_timeout_ms",
+            "HEALTH_CHECK_TIMEOUT_MS",
+            "2000",
+            "a positive integer",
+            |ms| *ms > 0,
+        );
+
+        Self {
+            check_timeout: Duration::from_millis(timeout_ms),
+        }
+    }
+}
+
+impl ShutdownConfig {
+    fn load(loader: &mut Loader) -> Self {
+        let grace_secs: u64 = loader.parse(
+            "shutdown.grace_period_secs",
+            "SHUTDOWN_GRACE_PERIOD_SECS",
+            "25",
+            "an integer",
+        );
+
+        Self {
+            grace_period: Duration::from_secs(grace_secs),
+        }
+    }
 }
@@ -0,0 +1,187 @@
+//
+//  media-savant-api
+//  config/reload.rs
+//
+
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::web;
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::Config;
+use crate::redact::sanitize;
+use crate::state::AppState;
+
+/// Editors and deploy tools often write a file in several steps; changes arriving within this
+/// window are applied together.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Outcome of the most recent reload, for the readiness check.
+#[derive(Default)]
+pub struct ReloadStatus {
+    /// Set while a changed configuration has been seen but not yet applied.
+    pending: AtomicBool,
+    last_error: Mutex<Option<String>>,
+    restart_required: Mutex<Vec<&'static str>>,
+}
+
+impl ReloadStatus {
+    pub fn pending(&self) -> bool {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    /// Why the last reload was rejected, if it was.
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    /// Changed settings that are still running with their old values.
+    pub fn restart_required(&self) -> Vec<&'static str> {
+        self.restart_required.lock().unwrap().clone()
+    }
+
+    /// Records a rejected reload. The running settings stay, so nothing is pending anymore.
+    fn reject(&self, message: String) {
+        warn!("Configuration not reloaded, keeping the running settings:\n{message}");
+        *self.last_error.lock().unwrap() = Some(message);
+        self.pending.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Reloads the configuration on `SIGHUP` and whenever the file at `path`, or the TLS
+/// certificate or key, changes.
+///
+/// A new configuration is only applied once it loads and validates completely; otherwise the
+/// running settings stay in place and the error is reported through `ReloadStatus`.
+pub fn watch(state: web::Data<AppState>, path: PathBuf, required: bool) {
+    let (tx, mut rx) = mpsc::channel::<()>(1);
+
+    match signal(SignalKind::hangup()) {
+        Ok(mut hangups) => {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                while hangups.recv().await.is_some() {
+                    let _ = tx.try_send(());
+                }
+            });
+        }
+        Err(err) => warn!("Cannot listen for SIGHUP: {err}"),
+    }
+    let mut watched = vec![path.clone()];
+    if let Some(certs) = &state.certs {
+        watched.extend(certs.paths().map(Path::to_path_buf));
+    }
+    let watcher = watch_files(&watched, tx);
+
+    tokio::spawn(async move {
+        // Dropping the watcher would stop file events.
+        let _watcher = watcher;
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+            reload(&state, &path, required);
+        }
+    });
+}
+
+/// Watches the directories rather than the files themselves, so replacing a file (as editors,
+/// certificate renewal and Kubernetes ConfigMaps do) keeps triggering reloads.
+fn watch_files(paths: &[PathBuf], tx: mpsc::Sender<()>) -> Option<RecommendedWatcher> {
+    let names: HashSet<OsString> = paths
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(OsStr::to_os_string)
+        .collect();
+    let dirs: HashSet<&Path> = paths
+        .iter()
+        .map(|path| {
+            path.parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."))
+        })
+        .collect();
+
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        // Reading the files ourselves must not count as a change.
+        let relevant_kind = matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_)
+        ) && !matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_)));
+        // ConfigMaps swap a `..data` symlink instead of touching the file.
+        let relevant_path = event.paths.iter().any(|changed| {
+            changed.file_name().is_some_and(|changed| {
+                names.contains(changed) || changed.to_string_lossy().starts_with("..")
+            })
+        });
+        if relevant_kind && relevant_path {
+            let _ = tx.try_send(());
+        }
+    });
+
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            warn!("Cannot watch the config file, reload with SIGHUP instead: {err}");
+            return None;
+        }
+    };
+    for dir in dirs {
+        if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            info!(
+                "Not watching {} for changes ({err}); reload with SIGHUP instead",
+                dir.display()
+            );
+        }
+    }
+    Some(watcher)
+}
+
+fn reload(state: &AppState, path: &Path, required: bool) {
+    if let Some(Err(err)) = state.certs.as_ref().map(|certs| certs.reload()) {
+        warn!("Keeping the current TLS certificate: {err:#}");
+    }
+
+    let status = &state.reload;
+    status.pending.store(true, Ordering::Relaxed);
+
+    let next = match Config::load(path, required) {
+        Ok(next) => next,
+        Err(errors) => return status.reject(sanitize(&errors.to_string())),
+    };
+
+    if let Err(err) = state.transport.reconfigure(&next) {
+        return status.reject(sanitize(&format!("{err:#}")));
+    }
+
+    let running = state.config();
+    let restart_required = running.restart_required(&next);
+    let next = running.with_restart_only_from(next);
+
+    state.metadata.reconfigure(next.metadata_cache.clone());
+    state.rate_limiter.reconfigure(&next.rate_limit);
+    state.config.store(Arc::new(next));
+
+    if restart_required.is_empty() {
+        info!("Configuration reloaded");
+    } else {
+        warn!(
+            "Configuration reloaded; restart to apply changes to {}",
+            restart_required.join(", ")
+        );
+    }
+    *status.last_error.lock().unwrap() = None;
+    *status.restart_required.lock().unwrap() = restart_required;
+    status.pending.store(false, Ordering::Relaxed);
+}
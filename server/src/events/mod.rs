@@ -0,0 +1,226 @@
+//
+//  media-savant-api
+//  events/mod.rs
+//
+
+mod relay;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast;
+
+use crate::jellyfin::websocket_url;
+use crate::models::{ServerEvent, SessionData};
+use crate::routes::auth::build_token_header;
+use crate::state::AppState;
+
+/// Events buffered per relay before slow browsers start missing some.
+const EVENT_BUFFER: usize = 64;
+
+/// Jellyfin message types worth pushing to browsers.
+pub const RELAYED_MESSAGES: &[&str] = &[
+    "LibraryChanged",
+    "UserDataChanged",
+    "Play",
+    "Playstate",
+    "GeneralCommand",
+    "RefreshProgress",
+    "SyncPlayCommand",
+    "SyncPlayGroupUpdate",
+    "ServerRestarting",
+    "ServerShuttingDown",
+    "RestartRequired",
+];
+
+/// Owns one Jellyfin `/socket` connection per device and fans its messages out to every
+/// browser tab signed in as that device.
+///
+/// Jellyfin sends profile-wide messages such as `LibraryChanged` to each of the user's sockets,
+/// but remote-control and SyncPlay commands only to the socket of the device they target, so
+/// devices never share a relay. Relays start with their first subscriber and shut down once the
+/// last one has left. The socket authenticates as the device's most recent session, so it keeps
+/// reconnecting after an older session of the same device signs out.
+#[derive(Default)]
+pub struct EventHub {
+    relays: Mutex<HashMap<String, Relay>>,
+}
+
+struct Relay {
+    sender: broadcast::Sender<ServerEvent>,
+    upstream: Arc<Mutex<relay::Upstream>>,
+}
+
+impl EventHub {
+    pub fn subscribe(
+        self: &Arc<Self>,
+        state: &AppState,
+        session: &SessionData,
+    ) -> broadcast::Receiver<ServerEvent> {
+        let upstream = relay::Upstream {
+            url: websocket_url(
+                &session.server_url,
+                &format!("/socket?deviceId={}", session.device_id),
+            ),
+            auth_header: build_token_header(state, session),
+            tls: state.transport.tls_config(&session.server_url),
+        };
+        self.attach(relay_key(session), upstream)
+    }
+
+    /// Joins the relay for `key`, starting one connected to `upstream` if there is none.
+    fn attach(
+        self: &Arc<Self>,
+        key: String,
+        upstream: relay::Upstream,
+    ) -> broadcast::Receiver<ServerEvent> {
+        let mut relays = self.relays.lock().unwrap();
+        if let Some(relay) = relays.get(&key) {
+            // Used from the next reconnect on.
+            *relay.upstream.lock().unwrap() = upstream;
+            return relay.sender.subscribe();
+        }
+
+        let (sender, receiver) = broadcast::channel(EVENT_BUFFER);
+        let upstream = Arc::new(Mutex::new(upstream));
+        relays.insert(
+            key.clone(),
+            Relay {
+                sender: sender.clone(),
+                upstream: upstream.clone(),
+            },
+        );
+        tokio::spawn(relay::run(self.clone(), key, upstream, sender));
+        receiver
+    }
+
+    /// Forgets the relay if nobody is listening; checked under the lock `subscribe` takes, so
+    /// a browser can never attach to a relay that is about to stop.
+    fn remove_if_idle(&self, key: &str, sender: &broadcast::Sender<ServerEvent>) -> bool {
+        let mut relays = self.relays.lock().unwrap();
+        if sender.receiver_count() > 0 {
+            return false;
+        }
+        relays.remove(key);
+        true
+    }
+}
+
+fn relay_key(session: &SessionData) -> String {
+    format!(
+        "{}|{}|{}",
+        session.server_url.trim_end_matches('/'),
+        session.user_id,
+        session.device_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::Secret;
+    use futures_util::{SinkExt, StreamExt};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message;
+    use uuid::Uuid;
+
+    fn session(device_id: &str) -> SessionData {
+        SessionData {
+            session_id: Uuid::new_v4(),
+            user_id: "user".to_string(),
+            username: "user".to_string(),
+            access_token: Secret::new("token".to_string()),
+            server_url: "http://jellyfin".to_string(),
+            device_id: device_id.to_string(),
+        }
+    }
+
+    /// Stands in for Jellyfin's `/socket`: like Jellyfin, it sends each connection the
+    /// commands targeting its own device, followed by a message for the whole profile.
+    async fn fake_jellyfin() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    // The device is in the request line, `GET /socket?deviceId=<id> HTTP/1.1`.
+                    let mut head = [0; 256];
+                    let read = stream.peek(&mut head).await.unwrap_or(0);
+                    let device = String::from_utf8_lossy(&head[..read])
+                        .split_once("deviceId=")
+                        .and_then(|(_, rest)| rest.split(' ').next())
+                        .unwrap_or("")
+                        .to_string();
+                    let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    for (kind, data) in [
+                        ("SyncPlayCommand", serde_json::json!({ "Device": device })),
+                        ("LibraryChanged", serde_json::json!({})),
+                    ] {
+                        let text = serde_json::json!({ "MessageType": kind, "Data": data });
+                        let _ = socket.send(Message::text(text.to_string())).await;
+                    }
+                    while let Some(Ok(_)) = socket.next().await {}
+                });
+            }
+        });
+        format!("ws://{address}")
+    }
+
+    fn upstream(base: &str, session: &SessionData) -> relay::Upstream {
+        let tls = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(rustls::RootCertStore::empty())
+        .with_no_client_auth();
+        relay::Upstream {
+            url: format!("{base}/socket?deviceId={}", session.device_id),
+            auth_header: "MediaBrowser Token=\"token\"".to_string(),
+            tls: Arc::new(tls),
+        }
+    }
+
+    /// Collects the devices of the SyncPlay commands received up to the profile-wide message.
+    async fn commands_until_library_change(
+        receiver: &mut broadcast::Receiver<ServerEvent>,
+    ) -> Vec<String> {
+        let mut devices = Vec::new();
+        loop {
+            let event = receiver.recv().await.unwrap();
+            match event.kind.as_str() {
+                "SyncPlayCommand" => {
+                    devices.push(event.data["Device"].as_str().unwrap().to_string())
+                }
+                "LibraryChanged" => return devices,
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_device_commands_only_to_that_device() {
+        let base = fake_jellyfin().await;
+        let hub = Arc::new(EventHub::default());
+        let living_room = session("living-room");
+        let phone = session("phone");
+
+        let mut living_room_events =
+            hub.attach(relay_key(&living_room), upstream(&base, &living_room));
+        let mut phone_events = hub.attach(relay_key(&phone), upstream(&base, &phone));
+
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            (
+                commands_until_library_change(&mut living_room_events).await,
+                commands_until_library_change(&mut phone_events).await,
+            )
+        })
+        .await
+        .expect("both relays deliver their events");
+        assert_eq!(received.0, ["living-room"]);
+        assert_eq!(received.1, ["phone"]);
+    }
+}
@@ -0,0 +1,158 @@
+//
+//  media-savant-api
+//  events/relay.rs
+//
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::time::{interval, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{
+    connect_async_tls_with_config, Connector, MaybeTlsStream, WebSocketStream,
+};
+use tracing::{info, warn};
+
+use crate::events::{EventHub, RELAYED_MESSAGES};
+use crate::models::ServerEvent;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Used until Jellyfin announces its own timeout with `ForceKeepAlive`.
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// How often a connected relay checks whether anyone is still listening.
+const IDLE_CHECK: Duration = Duration::from_secs(30);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Clone)]
+pub struct Upstream {
+    pub url: String,
+    pub auth_header: String,
+    pub tls: Arc<rustls::ClientConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct JellyfinMessage {
+    message_type: String,
+    #[serde(default)]
+    data: Value,
+}
+
+enum Disconnect {
+    /// The last subscriber left and the relay has been removed from the hub.
+    Idle,
+    Closed,
+}
+
+/// Keeps the upstream socket connected, reconnecting with exponential backoff, until no
+/// browser is subscribed any more.
+pub async fn run(
+    hub: Arc<EventHub>,
+    key: String,
+    upstream: Arc<Mutex<Upstream>>,
+    sender: broadcast::Sender<ServerEvent>,
+) {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        if hub.remove_if_idle(&key, &sender) {
+            break;
+        }
+        let upstream = upstream.lock().unwrap().clone();
+
+        match connect(&upstream).await {
+            Ok(socket) => {
+                info!("Event relay connected to {}", upstream.url);
+                backoff = INITIAL_BACKOFF;
+                let _ = sender.send(ServerEvent::connection("connected"));
+                match pump(socket, &hub, &key, &sender).await {
+                    Ok(Disconnect::Idle) => break,
+                    Ok(Disconnect::Closed) => {
+                        warn!("Jellyfin closed event socket {}", upstream.url)
+                    }
+                    Err(err) => warn!("Event socket {} failed: {err}", upstream.url),
+                }
+            }
+            Err(err) => warn!("Failed to connect event socket {}: {err}", upstream.url),
+        }
+
+        let _ = sender.send(ServerEvent::connection("reconnecting"));
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+    info!("Event relay for {key} stopped, no subscribers left");
+}
+
+async fn connect(upstream: &Upstream) -> Result<Socket, tungstenite::Error> {
+    let mut request = upstream.url.as_str().into_client_request()?;
+    let auth_header = HeaderValue::from_str(&upstream.auth_header)
+        .map_err(|err| tungstenite::Error::HttpFormat(err.into()))?;
+    request
+        .headers_mut()
+        .insert("X-Emby-Authorization", auth_header);
+
+    let connector = Connector::Rustls(upstream.tls.clone());
+    let (socket, _) = connect_async_tls_with_config(request, None, false, Some(connector)).await?;
+    Ok(socket)
+}
+
+async fn pump(
+    socket: Socket,
+    hub: &EventHub,
+    key: &str,
+    sender: &broadcast::Sender<ServerEvent>,
+) -> Result<Disconnect, tungstenite::Error> {
+    let (mut sink, mut stream) = socket.split();
+    let mut keep_alive = ticker(DEFAULT_KEEP_ALIVE);
+    let mut idle_check = ticker(IDLE_CHECK);
+
+    loop {
+        tokio::select! {
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let Ok(message) = serde_json::from_str::<JellyfinMessage>(text.as_str()) else {
+                        continue;
+                    };
+                    if message.message_type == "ForceKeepAlive" {
+                        // Jellyfin drops sockets silent for this many seconds; ping at half.
+                        let timeout = message.data.as_u64().unwrap_or(60).max(2);
+                        keep_alive = ticker(Duration::from_secs(timeout / 2));
+                    } else if RELAYED_MESSAGES.contains(&message.message_type.as_str()) {
+                        let _ = sender.send(ServerEvent {
+                            kind: message.message_type,
+                            data: message.data,
+                        });
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => return Ok(Disconnect::Closed),
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err),
+            },
+            _ = keep_alive.tick() => {
+                sink.send(Message::text(r#"{"MessageType":"KeepAlive"}"#)).await?;
+            }
+            _ = idle_check.tick() => {
+                if hub.remove_if_idle(key, sender) {
+                    let _ = sink.close().await;
+                    return Ok(Disconnect::Idle);
+                }
+            }
+        }
+    }
+}
+
+/// An interval whose first tick is one period away rather than immediate.
+fn ticker(period: Duration) -> Interval {
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker.reset();
+    ticker
+}
@@ -0,0 +1,234 @@
+//
+//  media-savant-api
+//  images/cache.rs
+//
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+
+/// Artwork cached on disk, evicted least recently used first once `max_bytes` is exceeded.
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    index: Mutex<CacheIndex>,
+    /// Distinguishes the temporary files of concurrent writes to the same key.
+    writes: AtomicU64,
+    /// Limits concurrent transcodes to the number of CPUs, since each one saturates a core.
+    transcodes: Arc<Semaphore>,
+}
+
+pub struct CachedImage {
+    pub bytes: Bytes,
+    pub content_type: String,
+}
+
+#[derive(Default)]
+struct CacheIndex {
+    entries: HashMap<String, CacheEntry>,
+    total_bytes: u64,
+    /// Logical clock used to order entries by last use.
+    clock: u64,
+}
+
+struct CacheEntry {
+    file_name: String,
+    size: u64,
+    stored_at: SystemTime,
+    last_used: u64,
+}
+
+impl ImageCache {
+    /// Opens the cache directory, indexing whatever a previous run left behind.
+    pub fn open(dir: &Path, max_bytes: u64) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let Some(file_name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if !metadata.is_file() || file_name.ends_with(".tmp") {
+                continue;
+            }
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((file_name, metadata.len(), modified));
+        }
+        files.sort_by_key(|(_, _, modified)| *modified);
+
+        let mut index = CacheIndex::default();
+        for (file_name, size, stored_at) in files {
+            let Some((key, _)) = file_name.rsplit_once('.') else {
+                continue;
+            };
+            index.clock += 1;
+            index.total_bytes += size;
+            index.entries.insert(
+                key.to_string(),
+                CacheEntry {
+                    file_name,
+                    size,
+                    stored_at,
+                    last_used: index.clock,
+                },
+            );
+        }
+        info!(
+            "Image cache at {} holds {} files ({} bytes)",
+            dir.display(),
+            index.entries.len(),
+            index.total_bytes
+        );
+
+        let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+        let cache = Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            index: Mutex::new(index),
+            writes: AtomicU64::new(0),
+            transcodes: Arc::new(Semaphore::new(cpus)),
+        };
+        cache.evict();
+        Ok(cache)
+    }
+
+    /// Returns the cached image if present and, when `max_age` is set, still fresh.
+    pub async fn get(&self, key: &str, max_age: Option<Duration>) -> Option<CachedImage> {
+        let file_name = {
+            let mut index = self.index.lock().unwrap();
+            index.clock += 1;
+            let clock = index.clock;
+            let entry = index.entries.get_mut(key)?;
+            if max_age
+                .is_some_and(|max_age| entry.stored_at.elapsed().unwrap_or(Duration::MAX) > max_age)
+            {
+                return None;
+            }
+            entry.last_used = clock;
+            entry.file_name.clone()
+        };
+
+        match tokio::fs::read(self.dir.join(&file_name)).await {
+            Ok(bytes) => Some(CachedImage {
+                bytes: Bytes::from(bytes),
+                content_type: content_type_for(&file_name).to_string(),
+            }),
+            Err(err) => {
+                warn!("Dropping unreadable cached image {file_name}: {err}");
+                self.forget(key);
+                None
+            }
+        }
+    }
+
+    /// Stores an image, then evicts the least recently used entries over budget.
+    pub async fn put(&self, key: &str, content_type: &str, bytes: &Bytes) {
+        let file_name = format!("{key}.{}", extension_for(content_type));
+        let path = self.dir.join(&file_name);
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+        let temp_path = self.dir.join(format!("{file_name}.{write}.tmp"));
+
+        // Write then rename so a concurrent reader never sees a partial file.
+        let written = async {
+            tokio::fs::write(&temp_path, bytes).await?;
+            tokio::fs::rename(&temp_path, &path).await
+        }
+        .await;
+        if let Err(err) = written {
+            warn!("Failed to cache image {file_name}: {err}");
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return;
+        }
+
+        {
+            let mut index = self.index.lock().unwrap();
+            index.clock += 1;
+            let entry = CacheEntry {
+                file_name,
+                size: bytes.len() as u64,
+                stored_at: SystemTime::now(),
+                last_used: index.clock,
+            };
+            index.total_bytes += entry.size;
+            if let Some(previous) = index.entries.insert(key.to_string(), entry) {
+                index.total_bytes -= previous.size;
+                if previous.file_name != index.entries[key].file_name {
+                    let _ = std::fs::remove_file(self.dir.join(previous.file_name));
+                }
+            }
+        }
+        self.evict();
+    }
+
+    /// Waits for a free transcoding slot; hold the permit until the transcode finishes.
+    pub async fn transcode_permit(&self) -> OwnedSemaphorePermit {
+        self.transcodes
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the transcode semaphore is never closed")
+    }
+
+    fn forget(&self, key: &str) {
+        let mut index = self.index.lock().unwrap();
+        if let Some(entry) = index.entries.remove(key) {
+            index.total_bytes -= entry.size;
+        }
+    }
+
+    fn evict(&self) {
+        let mut index = self.index.lock().unwrap();
+        if index.total_bytes <= self.max_bytes {
+            return;
+        }
+
+        let mut by_age: Vec<(u64, String)> = index
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_age.sort_unstable();
+
+        for (_, key) in by_age {
+            if index.total_bytes <= self.max_bytes {
+                break;
+            }
+            if let Some(entry) = index.entries.remove(&key) {
+                index.total_bytes -= entry.size;
+                if let Err(err) = std::fs::remove_file(self.dir.join(&entry.file_name)) {
+                    warn!("Failed to evict cached image {}: {err}", entry.file_name);
+                }
+            }
+        }
+    }
+}
+
+fn extension_for(content_type: &str) -> &'static str {
+    match content_type.split(';').next().unwrap_or("").trim() {
+        "image/png" => "png",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        _ => "jpg",
+    }
+}
+
+fn content_type_for(file_name: &str) -> &'static str {
+    match file_name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        _ => "image/jpeg",
+    }
+}
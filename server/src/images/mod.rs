@@ -0,0 +1,116 @@
+//
+//  media-savant-api
+//  images/mod.rs
+//
+
+mod cache;
+mod placeholder;
+mod transcode;
+
+pub use cache::ImageCache;
+pub use placeholder::{average_color, encode_blur_hash, is_valid_blur_hash, render_placeholder};
+pub use transcode::{is_transcodable, negotiate, raster_content_type, transcode};
+
+use std::fmt::Write as _;
+
+use ring::digest::{digest, SHA256};
+
+use crate::models::ImageQuery;
+
+/// Jellyfin image types the artwork route accepts.
+pub const IMAGE_TYPES: &[&str] = &[
+    "Primary",
+    "Backdrop",
+    "Thumb",
+    "Logo",
+    "Banner",
+    "Art",
+    "Disc",
+    "Box",
+    "BoxRear",
+    "Screenshot",
+    "Menu",
+    "Chapter",
+    "Profile",
+];
+
+/// Builds the cache key for one rendition of an item's artwork on the server at `server_url`.
+///
+/// Item IDs and tags are only unique per server, and users sign in to any server they like,
+/// so the key starts with a hash of the server's URL. For tagged artwork the key doubles as
+/// the strong ETag: Jellyfin changes an image's tag whenever the artwork changes, so the same
+/// key always maps to the same bytes.
+pub fn cache_key(server_url: &str, item_id: &str, image_type: &str, query: &ImageQuery) -> String {
+    let dimension = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+    let key = format!(
+        "{}_{item_id}_{image_type}_{}_{}_{}x{}_{}x{}_q{}",
+        server_hash(server_url),
+        query.index.unwrap_or(0),
+        query.tag.as_deref().unwrap_or("untagged"),
+        dimension(query.max_width),
+        dimension(query.max_height),
+        dimension(query.fill_width),
+        dimension(query.fill_height),
+        dimension(query.quality),
+    );
+
+    key.chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
+                ch
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// First 16 hex digits of the SHA-256 of the server URL, normalized so that spellings of the
+/// same server (host case, default port, trailing slash) share one cache.
+fn server_hash(server_url: &str) -> String {
+    let normalized = reqwest::Url::parse(server_url)
+        .map(|url| url.as_str().trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| server_url.trim_end_matches('/').to_string());
+    let digest = digest(&SHA256, normalized.as_bytes());
+    digest.as_ref()[..8]
+        .iter()
+        .fold(String::with_capacity(16), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_artwork_by_server() {
+        let query = ImageQuery {
+            tag: Some("abc".to_string()),
+            ..Default::default()
+        };
+        let key = |server| cache_key(server, "item", "Primary", &query);
+        assert_eq!(key("http://Jellyfin:80/"), key("http://jellyfin"));
+        assert_ne!(key("http://jellyfin"), key("http://other-jellyfin"));
+        assert!(key("http://jellyfin").ends_with("_item_Primary_0_abc_x_x_q"));
+    }
+
+    #[test]
+    fn serves_only_raster_images() {
+        assert_eq!(
+            raster_content_type(b"\x89PNG\r\n\x1a\n...."),
+            Some("image/png")
+        );
+        assert_eq!(
+            raster_content_type(b"\xff\xd8\xff\xe0...."),
+            Some("image/jpeg")
+        );
+        assert_eq!(raster_content_type(b"GIF89a...."), Some("image/gif"));
+        assert_eq!(
+            raster_content_type(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            None
+        );
+        assert_eq!(raster_content_type(b"<html><script></script></html>"), None);
+    }
+}
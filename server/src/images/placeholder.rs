@@ -0,0 +1,73 @@
+//
+//  media-savant-api
+//  images/placeholder.rs
+//
+
+use std::io::Cursor;
+
+use image::{ImageFormat, RgbaImage};
+
+/// Components used when hashing artwork ourselves, matching what Jellyfin generates.
+const COMPONENTS_X: u32 = 4;
+const COMPONENTS_Y: u32 = 3;
+/// Artwork is shrunk to this size before hashing; a BlurHash keeps no detail beyond it.
+const HASH_SOURCE_SIZE: u32 = 64;
+
+const BASE83: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Average colour of a BlurHash as `#rrggbb`, read straight from its DC component.
+pub fn average_color(blur_hash: &str) -> Option<String> {
+    if !is_valid_blur_hash(blur_hash) {
+        return None;
+    }
+    let value = decode_base83(&blur_hash[2..6])?;
+    Some(format!("#{value:06x}"))
+}
+
+/// Checks the hash length against the component count encoded in its first character.
+pub fn is_valid_blur_hash(blur_hash: &str) -> bool {
+    let Some(size) = blur_hash.get(..1).and_then(decode_base83) else {
+        return false;
+    };
+    let components_x = size % 9 + 1;
+    let components_y = size / 9 + 1;
+    blur_hash.len() == (4 + 2 * components_x * components_y) as usize
+        && blur_hash.bytes().all(|ch| BASE83.contains(&ch))
+}
+
+/// Computes a BlurHash for encoded artwork. CPU bound; run it off the async workers.
+pub fn encode_blur_hash(bytes: &[u8]) -> Result<String, String> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|err| err.to_string())?
+        .thumbnail(HASH_SOURCE_SIZE, HASH_SOURCE_SIZE)
+        .to_rgba8();
+    blurhash::encode(
+        COMPONENTS_X,
+        COMPONENTS_Y,
+        image.width(),
+        image.height(),
+        image.as_raw(),
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Renders a BlurHash as a PNG of the given size.
+pub fn render_placeholder(blur_hash: &str, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let pixels = blurhash::decode(blur_hash, width, height, 1.0).map_err(|err| err.to_string())?;
+    let image = RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| "Decoded placeholder has the wrong size".to_string())?;
+
+    let mut output = Cursor::new(Vec::new());
+    image
+        .write_to(&mut output, ImageFormat::Png)
+        .map_err(|err| err.to_string())?;
+    Ok(output.into_inner())
+}
+
+fn decode_base83(value: &str) -> Option<u32> {
+    value.bytes().try_fold(0u32, |acc, ch| {
+        let digit = BASE83.iter().position(|&c| c == ch)? as u32;
+        acc.checked_mul(83)?.checked_add(digit)
+    })
+}
@@ -0,0 +1,116 @@
+//
+//  media-savant-api
+//  images/transcode.rs
+//
+
+use image::codecs::avif::AvifEncoder;
+use image::ImageFormat;
+
+use crate::config::ImageCacheConfig;
+
+/// Modern formats artwork can be re-encoded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Avif,
+    WebP,
+}
+
+impl OutputFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Avif => "image/avif",
+            Self::WebP => "image/webp",
+        }
+    }
+
+    /// Suffix distinguishing this variant's cache key from the original's.
+    pub fn key_suffix(self) -> &'static str {
+        match self {
+            Self::Avif => "avif",
+            Self::WebP => "webp",
+        }
+    }
+}
+
+/// Picks the best format the client explicitly accepts, preferring AVIF unless the client
+/// weights WebP higher. Wildcards do not count: browsers list the formats they decode.
+pub fn negotiate(accept: Option<&str>, config: &ImageCacheConfig) -> Option<OutputFormat> {
+    if !config.transcode {
+        return None;
+    }
+
+    let mut avif = 0.0f32;
+    let mut webp = 0.0f32;
+    for range in accept?.split(',') {
+        let mut parts = range.split(';');
+        let media_type = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let weight = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|value| value.parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        match media_type.as_str() {
+            "image/avif" => avif = avif.max(weight),
+            "image/webp" => webp = webp.max(weight),
+            _ => {}
+        }
+    }
+
+    if avif > 0.0 && avif >= webp {
+        Some(OutputFormat::Avif)
+    } else if webp > 0.0 {
+        Some(OutputFormat::WebP)
+    } else {
+        None
+    }
+}
+
+/// The content type of a raster image Jellyfin returned, judged by its bytes rather than
+/// Jellyfin's header. Anything else, such as SVG that could carry script, is not served.
+pub fn raster_content_type(bytes: &[u8]) -> Option<&'static str> {
+    match image::guess_format(bytes).ok()? {
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::WebP => Some("image/webp"),
+        ImageFormat::Avif => Some("image/avif"),
+        ImageFormat::Bmp => Some("image/bmp"),
+        _ => None,
+    }
+}
+
+/// Whether an upstream image can be usefully re-encoded; animations and modern formats are
+/// kept.
+pub fn is_transcodable(content_type: &str) -> bool {
+    matches!(
+        content_type.split(';').next().unwrap_or("").trim(),
+        "image/jpeg" | "image/jpg" | "image/png" | "image/bmp"
+    )
+}
+
+/// Decodes `bytes` and re-encodes them as `format`. CPU bound; run it off the async workers.
+pub fn transcode(
+    bytes: &[u8],
+    format: OutputFormat,
+    config: &ImageCacheConfig,
+) -> Result<Vec<u8>, image::ImageError> {
+    let image = image::load_from_memory(bytes)?;
+
+    match format {
+        OutputFormat::Avif => {
+            let mut output = Vec::new();
+            let encoder = AvifEncoder::new_with_speed_quality(
+                &mut output,
+                config.avif_speed,
+                config.avif_quality,
+            );
+            image.write_with_encoder(encoder)?;
+            Ok(output)
+        }
+        OutputFormat::WebP => {
+            let rgba = image.to_rgba8();
+            let encoder = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height());
+            Ok(encoder.encode(config.webp_quality).to_vec())
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//
+//  media-savant-api
+//  jellyfin/breaker.rs
+//
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use super::{is_transient, Traffic};
+use crate::config::{Config, WakeTarget};
+use crate::models::CircuitState;
+use crate::state::AppState;
+use crate::wol;
+
+/// How long a server missing from `jellyfin.servers` is pinged before its probe gives up.
+const AD_HOC_PROBE_LIMIT: Duration = Duration::from_secs(10 * 60);
+
+/// Per-server circuit breakers for Jellyfin.
+///
+/// After `upstream.breaker_failures` consecutive connection failures, timeouts or gateway
+/// errors, a server's circuit opens: requests to it fail straight away instead of each
+/// waiting out the connect timeout, while a background task pings the server and closes the
+/// circuit once it answers.
+///
+/// Servers configured with a MAC address skip the count: the first connection attempt that
+/// fails sends a Wake-on-LAN packet and the circuit reports `waking` until the server answers or
+/// `upstream.wake_timeout_secs` passes.
+///
+/// Servers users sign in to ad hoc are only probed for `AD_HOC_PROBE_LIMIT`; after that their
+/// circuit is dropped, and requests find out for themselves whether the server is back.
+#[derive(Default)]
+pub struct CircuitBreakers {
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+#[derive(Default)]
+struct Circuit {
+    failures: u32,
+    state: Option<CircuitState>,
+}
+
+impl CircuitBreakers {
+    pub fn state(&self, server: &str) -> CircuitState {
+        let circuits = self.circuits.lock().unwrap();
+        circuits
+            .get(server)
+            .and_then(|circuit| circuit.state)
+            .unwrap_or(CircuitState::Closed)
+    }
+
+    fn open_count(&self) -> usize {
+        let circuits = self.circuits.lock().unwrap();
+        circuits
+            .values()
+            .filter(|circuit| circuit.state.is_some())
+            .count()
+    }
+
+    /// Counts a failure; returns the state the circuit just moved to, if it tripped. `wakes`
+    /// trips it straight to `Waking`. Failures while the circuit is already open are not
+    /// counted, as only the probe closes it again.
+    fn record_failure(&self, server: &str, threshold: u32, wakes: bool) -> Option<CircuitState> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(server.to_string()).or_default();
+        if circuit.state.is_some() {
+            return None;
+        }
+        circuit.failures = circuit.failures.saturating_add(1);
+        circuit.state = if wakes {
+            Some(CircuitState::Waking)
+        } else if threshold > 0 && circuit.failures >= threshold {
+            Some(CircuitState::Open)
+        } else {
+            None
+        };
+        circuit.state
+    }
+
+    /// Resets the failure count. Only the probe closes an open circuit, so a request that
+    /// slipped through before it opened doesn't end the fast failing early.
+    fn record_success(&self, server: &str) {
+        let mut circuits = self.circuits.lock().unwrap();
+        if circuits
+            .get(server)
+            .is_some_and(|circuit| circuit.state.is_none())
+        {
+            circuits.remove(server);
+        }
+    }
+
+    /// Stops reporting `waking` for a server that never answered.
+    fn give_up_waking(&self, server: &str) {
+        let mut circuits = self.circuits.lock().unwrap();
+        if let Some(circuit) = circuits
+            .get_mut(server)
+            .filter(|circuit| circuit.state == Some(CircuitState::Waking))
+        {
+            circuit.state = Some(CircuitState::Open);
+        }
+    }
+
+    fn close(&self, server: &str) {
+        self.circuits.lock().unwrap().remove(server);
+    }
+}
+
+/// The server a request URL belongs to: the configured server whose URL it starts with, or
+/// else the URL's origin. Ad hoc servers behind a path prefix are pinged at the origin.
+pub fn server_for(url: &reqwest::Url, config: &Config) -> String {
+    config
+        .jellyfin
+        .servers
+        .iter()
+        .find(|server| {
+            url.as_str()
+                .strip_prefix(server.url.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+        })
+        .map_or_else(
+            || url.origin().ascii_serialization(),
+            |server| server.url.clone(),
+        )
+}
+
+fn is_configured(config: &Config, server: &str) -> bool {
+    config
+        .jellyfin
+        .servers
+        .iter()
+        .any(|configured| configured.url == server)
+}
+
+fn wake_target(config: &Config, server: &str) -> Option<WakeTarget> {
+    config
+        .jellyfin
+        .servers
+        .iter()
+        .find(|configured| configured.url == server)
+        .and_then(|configured| configured.wake)
+}
+
+/// Updates the server's circuit with the outcome of a request, starting the recovery probe
+/// when the circuit trips.
+pub fn record(state: &AppState, server: &str, outcome: &reqwest::Result<reqwest::Response>) {
+    let breakers = &state.breakers;
+    if !is_transient(outcome) {
+        breakers.record_success(server);
+        return;
+    }
+
+    // A gateway error means something answered; only a server that can't be reached at all
+    // may be asleep.
+    let unreachable = outcome
+        .as_ref()
+        .is_err_and(|err| err.is_connect() || err.is_timeout());
+    let config = state.config();
+    let wakes = unreachable && wake_target(&config, server).is_some();
+    let tripped = breakers.record_failure(server, config.upstream.breaker_threshold, wakes);
+    let Some(tripped) = tripped else {
+        return;
+    };
+    match tripped {
+        CircuitState::Waking => info!("Jellyfin at {server} is unreachable; waking it up"),
+        _ => warn!("Jellyfin at {server} is unreachable; failing requests fast until it answers"),
+    }
+    state.metrics.record_circuit_opened();
+    state.metrics.set_open_circuits(breakers.open_count());
+    tokio::spawn(probe(state.clone(), server.to_string()));
+}
+
+/// Pings the server until it answers, then closes its circuit; an ad hoc server's circuit is
+/// dropped once `AD_HOC_PROBE_LIMIT` passes. While the server is waking, every round also
+/// resends the magic packet, in case one got lost.
+async fn probe(state: AppState, server: String) {
+    let started = Instant::now();
+    loop {
+        let config = state.config();
+        if state.breakers.state(&server) == CircuitState::Waking {
+            match wake_target(&config, &server) {
+                Some(target) if started.elapsed() < config.upstream.wake_timeout => {
+                    send_wake(&state, &server, target).await;
+                }
+                _ => {
+                    warn!(
+                        "Jellyfin at {server} did not wake up within {}s",
+                        config.upstream.wake_timeout.as_secs()
+                    );
+                    state.breakers.give_up_waking(&server);
+                }
+            }
+        }
+        tokio::time::sleep(config.upstream.breaker_probe_interval).await;
+
+        let ping = state
+            .transport
+            .client(&server, Traffic::Metadata)
+            .get(format!("{server}/System/Ping"))
+            .timeout(config.health.check_timeout)
+            .send()
+            .await;
+        if ping.is_ok_and(|response| response.status().is_success()) {
+            break;
+        }
+        if !is_configured(&config, &server) && started.elapsed() >= AD_HOC_PROBE_LIMIT {
+            state.breakers.close(&server);
+            state.metrics.set_open_circuits(state.breakers.open_count());
+            warn!(
+                "Stopped probing Jellyfin at {server} after {}s without an answer",
+                started.elapsed().as_secs()
+            );
+            return;
+        }
+    }
+
+    state.breakers.close(&server);
+    state.metrics.set_open_circuits(state.breakers.open_count());
+    info!(
+        "Jellyfin at {server} is reachable again after {}s",
+        started.elapsed().as_secs()
+    );
+}
+
+async fn send_wake(state: &AppState, server: &str, target: WakeTarget) {
+    match wol::send(target.mac, target.broadcast).await {
+        Ok(()) => state.metrics.record_wake_sent(),
+        Err(err) => warn!(
+            "Failed to send Wake-on-LAN packet for {server} to {}: {err}",
+            target.broadcast
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: &str = "http://jellyfin:8096";
+
+    fn failures(breakers: &CircuitBreakers) -> u32 {
+        breakers.circuits.lock().unwrap()[SERVER].failures
+    }
+
+    #[test]
+    fn opens_after_the_threshold() {
+        let breakers = CircuitBreakers::default();
+        assert_eq!(breakers.record_failure(SERVER, 3, false), None);
+        assert_eq!(breakers.record_failure(SERVER, 3, false), None);
+        assert_eq!(breakers.state(SERVER), CircuitState::Closed);
+        assert_eq!(
+            breakers.record_failure(SERVER, 3, false),
+            Some(CircuitState::Open)
+        );
+        assert_eq!(breakers.state(SERVER), CircuitState::Open);
+        assert_eq!(breakers.open_count(), 1);
+    }
+
+    #[test]
+    fn never_opens_with_a_zero_threshold() {
+        let breakers = CircuitBreakers::default();
+        for _ in 0..10 {
+            assert_eq!(breakers.record_failure(SERVER, 0, false), None);
+        }
+        assert_eq!(breakers.state(SERVER), CircuitState::Closed);
+    }
+
+    #[test]
+    fn stops_counting_once_open() {
+        let breakers = CircuitBreakers::default();
+        breakers.record_failure(SERVER, 1, false);
+        assert_eq!(breakers.record_failure(SERVER, 1, false), None);
+        assert_eq!(breakers.record_failure(SERVER, 1, true), None);
+        assert_eq!(failures(&breakers), 1);
+        assert_eq!(breakers.state(SERVER), CircuitState::Open);
+    }
+
+    #[test]
+    fn success_resets_the_count_while_closed() {
+        let breakers = CircuitBreakers::default();
+        breakers.record_failure(SERVER, 2, false);
+        breakers.record_success(SERVER);
+        assert_eq!(breakers.record_failure(SERVER, 2, false), None);
+        assert_eq!(
+            breakers.record_failure(SERVER, 2, false),
+            Some(CircuitState::Open)
+        );
+    }
+
+    #[test]
+    fn only_close_ends_an_open_circuit() {
+        let breakers = CircuitBreakers::default();
+        breakers.record_failure(SERVER, 1, false);
+        breakers.record_success(SERVER);
+        assert_eq!(breakers.state(SERVER), CircuitState::Open);
+
+        breakers.close(SERVER);
+        assert_eq!(breakers.state(SERVER), CircuitState::Closed);
+        assert_eq!(breakers.open_count(), 0);
+        assert_eq!(breakers.record_failure(SERVER, 2, false), None);
+    }
+
+    #[test]
+    fn wakes_on_the_first_failure_and_gives_up_to_open() {
+        let breakers = CircuitBreakers::default();
+        assert_eq!(
+            breakers.record_failure(SERVER, 5, true),
+            Some(CircuitState::Waking)
+        );
+        assert_eq!(breakers.state(SERVER), CircuitState::Waking);
+        breakers.record_success(SERVER);
+        assert_eq!(breakers.state(SERVER), CircuitState::Waking);
+
+        breakers.give_up_waking(SERVER);
+        assert_eq!(breakers.state(SERVER), CircuitState::Open);
+        breakers.close(SERVER);
+        assert_eq!(breakers.state(SERVER), CircuitState::Closed);
+    }
+
+    #[test]
+    fn giving_up_leaves_closed_circuits_alone() {
+        let breakers = CircuitBreakers::default();
+        breakers.record_failure(SERVER, 3, false);
+        breakers.give_up_waking(SERVER);
+        assert_eq!(breakers.state(SERVER), CircuitState::Closed);
+        assert_eq!(breakers.open_count(), 0);
+    }
+}
@@ -0,0 +1,411 @@
+//
+//  media-savant-api
+//  jellyfin/cache.rs
+//
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use bytes::Bytes;
+use serde::Serialize;
+
+use crate::config::MetadataCacheConfig;
+use crate::models::SessionData;
+
+/// In-memory cache for read-heavy Jellyfin metadata, partitioned per user.
+///
+/// Keys always start with the session's server and user, so one user's cached views can never
+/// be served to another, and invalidating a user only touches their own entries.
+pub struct MetadataCache {
+    config: ArcSwap<MetadataCacheConfig>,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+pub struct CachedResponse {
+    pub body: Bytes,
+    pub content_type: Option<String>,
+}
+
+struct CacheEntry {
+    body: Bytes,
+    content_type: Option<String>,
+    expires_at: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CacheClass {
+    Views,
+    Seasons,
+    Similar,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataCacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+}
+
+impl MetadataCache {
+    pub fn new(config: MetadataCacheConfig) -> Self {
+        Self {
+            config: ArcSwap::from_pointee(config),
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// How long a response to this request may be cached, or `None` if it must not be.
+    pub fn ttl_for(&self, method: &reqwest::Method, path: &str) -> Option<Duration> {
+        let config = self.config.load();
+        if !config.enabled || method != reqwest::Method::GET {
+            return None;
+        }
+        let ttl = match classify(path)? {
+            CacheClass::Views => config.views_ttl,
+            CacheClass::Seasons => config.seasons_ttl,
+            CacheClass::Similar => config.similar_ttl,
+        };
+        (!ttl.is_zero()).then_some(ttl)
+    }
+
+    /// The cache key for a request, shared by the proxy and the typed client so both hit the
+    /// same entries. Jellyfin matches paths and parameter names case-insensitively and ignores
+    /// parameter order, so the key does too.
+    pub fn key(session: &SessionData, path: &str, query: &str) -> String {
+        format!(
+            "{}{}?{}",
+            user_scope(session),
+            path.trim_start_matches('/').to_ascii_lowercase(),
+            normalize_query(query)
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        let cached = match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(CachedResponse {
+                body: entry.body.clone(),
+                content_type: entry.content_type.clone(),
+            }),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        let counter = if cached.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    pub fn put(&self, key: String, ttl: Duration, body: Bytes, content_type: Option<String>) {
+        let now = Instant::now();
+        let max_entries = self.config.load().max_entries;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        if entries.len() >= max_entries && !entries.contains_key(&key) {
+            // Still full of live entries: drop whichever would have expired first.
+            let soonest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone());
+            match soonest {
+                Some(soonest) => {
+                    entries.remove(&soonest);
+                }
+                None => return,
+            }
+        }
+
+        entries.insert(
+            key,
+            CacheEntry {
+                body,
+                content_type,
+                expires_at: now + ttl,
+            },
+        );
+    }
+
+    /// Switches to new settings. Entries cached under the old TTLs or size limit are dropped.
+    pub fn reconfigure(&self, config: MetadataCacheConfig) {
+        if **self.config.load() == config {
+            return;
+        }
+        self.config.store(Arc::new(config));
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Whether a write to this path changes data the cache may hold, such as played state.
+    pub fn invalidated_by(method: &reqwest::Method, path: &str) -> bool {
+        if method == reqwest::Method::GET || method == reqwest::Method::HEAD {
+            return false;
+        }
+        let segments = segments(path);
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        match segments.as_slice() {
+            // Progress reports arrive every few seconds and change nothing we cache.
+            ["sessions", "playing", "progress"] | ["sessions", "playing", "ping"] => false,
+            ["sessions", "playing", ..] => true,
+            _ => segments.iter().any(|segment| {
+                matches!(
+                    *segment,
+                    "playeditems"
+                        | "userplayeditems"
+                        | "favoriteitems"
+                        | "userfavoriteitems"
+                        | "userdata"
+                        | "rating"
+                )
+            }),
+        }
+    }
+
+    /// Drops every entry cached for the session's user.
+    pub fn invalidate_user(&self, session: &SessionData) {
+        let scope = user_scope(session);
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|key, _| !key.starts_with(&scope));
+    }
+
+    pub fn stats(&self) -> MetadataCacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        MetadataCacheStats {
+            enabled: self.config.load().enabled,
+            entries: self.entries.lock().unwrap().len(),
+            hits,
+            misses,
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+        }
+    }
+}
+
+fn user_scope(session: &SessionData) -> String {
+    format!(
+        "{}|{}|",
+        session.server_url.trim_end_matches('/'),
+        session.user_id
+    )
+}
+
+/// Decodes the query, sorts its parameters and re-encodes it, so `a=1&b=2`, `b=2&a=1` and
+/// `A=%31&b=2` all produce the same string.
+fn normalize_query(query: &str) -> String {
+    if query.is_empty() {
+        return String::new();
+    }
+    let mut url = reqwest::Url::parse("http://cache.invalid/").expect("valid placeholder URL");
+    url.set_query(Some(query));
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.into_owned()))
+        .collect();
+    pairs.sort();
+
+    url.set_query(None);
+    url.query_pairs_mut().extend_pairs(pairs);
+    url.query().unwrap_or("").to_string()
+}
+
+fn segments(path: &str) -> Vec<String> {
+    path.split('?')
+        .next()
+        .unwrap_or("")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+fn classify(path: &str) -> Option<CacheClass> {
+    let segments = segments(path);
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    match segments.as_slice() {
+        ["users", _, "views"] | ["userviews"] | ["library", "mediafolders"] => {
+            Some(CacheClass::Views)
+        }
+        ["shows", _, "seasons"] | ["shows", _, "episodes"] => Some(CacheClass::Seasons),
+        ["items" | "shows" | "movies" | "trailers" | "albums" | "artists", _, "similar"] => {
+            Some(CacheClass::Similar)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::Secret;
+    use reqwest::Method;
+    use uuid::Uuid;
+
+    fn session(user_id: &str) -> SessionData {
+        SessionData {
+            session_id: Uuid::nil(),
+            user_id: user_id.to_string(),
+            username: "user".to_string(),
+            access_token: Secret::new("token".to_string()),
+            server_url: "http://jellyfin:8096/".to_string(),
+            device_id: "device".to_string(),
+        }
+    }
+
+    #[test]
+    fn caches_only_listed_metadata() {
+        assert_eq!(classify("Users/abc/Views"), Some(CacheClass::Views));
+        assert_eq!(classify("/UserViews?userId=abc"), Some(CacheClass::Views));
+        assert_eq!(classify("Library/MediaFolders"), Some(CacheClass::Views));
+        assert_eq!(classify("Shows/abc/Seasons"), Some(CacheClass::Seasons));
+        assert_eq!(classify("shows/abc/episodes"), Some(CacheClass::Seasons));
+        assert_eq!(classify("Items/abc/Similar"), Some(CacheClass::Similar));
+        assert_eq!(classify("Movies/abc/Similar"), Some(CacheClass::Similar));
+
+        assert_eq!(classify("Items/abc"), None);
+        assert_eq!(classify("Users/abc/Items/Resume"), None);
+        assert_eq!(classify("Shows/abc/Seasons/extra"), None);
+        assert_eq!(classify("Videos/abc/stream"), None);
+    }
+
+    #[test]
+    fn invalidates_on_user_data_writes() {
+        assert!(MetadataCache::invalidated_by(
+            &Method::POST,
+            "Users/abc/PlayedItems/def"
+        ));
+        assert!(MetadataCache::invalidated_by(
+            &Method::DELETE,
+            "UserPlayedItems/def"
+        ));
+        assert!(MetadataCache::invalidated_by(
+            &Method::POST,
+            "Users/abc/FavoriteItems/def"
+        ));
+        assert!(MetadataCache::invalidated_by(
+            &Method::POST,
+            "UserItems/def/UserData"
+        ));
+        assert!(MetadataCache::invalidated_by(
+            &Method::POST,
+            "Users/abc/Items/def/Rating"
+        ));
+        assert!(MetadataCache::invalidated_by(
+            &Method::POST,
+            "Sessions/Playing"
+        ));
+        assert!(MetadataCache::invalidated_by(
+            &Method::POST,
+            "Sessions/Playing/Stopped"
+        ));
+    }
+
+    #[test]
+    fn ignores_reads_and_progress_reports() {
+        assert!(!MetadataCache::invalidated_by(
+            &Method::GET,
+            "Users/abc/PlayedItems/def"
+        ));
+        assert!(!MetadataCache::invalidated_by(
+            &Method::HEAD,
+            "UserPlayedItems/def"
+        ));
+        assert!(!MetadataCache::invalidated_by(
+            &Method::POST,
+            "Sessions/Playing/Progress"
+        ));
+        assert!(!MetadataCache::invalidated_by(
+            &Method::POST,
+            "Sessions/Playing/Ping"
+        ));
+        assert!(!MetadataCache::invalidated_by(
+            &Method::POST,
+            "Items/abc/Refresh"
+        ));
+    }
+
+    #[test]
+    fn keys_ignore_parameter_order_and_encoding() {
+        let session = session("abc");
+        let key = |path, query| MetadataCache::key(&session, path, query);
+        assert_eq!(
+            key("Shows/x/Seasons", "userId=abc&fields=Overview"),
+            key("/shows/x/seasons", "Fields=Overview&UserId=abc")
+        );
+        assert_eq!(
+            key("Shows/x/Seasons", "fields=Overview%2CPath"),
+            key("Shows/x/Seasons", "fields=Overview,Path")
+        );
+        assert_ne!(
+            key("Shows/x/Seasons", "fields=Overview"),
+            key("Shows/x/Seasons", "fields=Path")
+        );
+        assert_ne!(
+            key("Shows/x/Seasons", "a=1%262"),
+            key("Shows/x/Seasons", "a=1&2")
+        );
+        assert_eq!(key("UserViews", ""), "http://jellyfin:8096|abc|userviews?");
+    }
+
+    #[test]
+    fn keys_are_scoped_to_the_user() {
+        let cache = MetadataCache::new(MetadataCacheConfig {
+            enabled: true,
+            max_entries: 10,
+            views_ttl: Duration::from_secs(60),
+            seasons_ttl: Duration::from_secs(60),
+            similar_ttl: Duration::from_secs(60),
+        });
+        let alice = session("alice");
+        let bob = session("bob");
+        let ttl = cache.ttl_for(&Method::GET, "UserViews").unwrap();
+        cache.put(
+            MetadataCache::key(&alice, "UserViews", ""),
+            ttl,
+            Bytes::new(),
+            None,
+        );
+        cache.put(
+            MetadataCache::key(&bob, "UserViews", ""),
+            ttl,
+            Bytes::new(),
+            None,
+        );
+
+        assert!(cache
+            .get(&MetadataCache::key(&bob, "userviews", ""))
+            .is_some());
+        cache.invalidate_user(&alice);
+        assert!(cache
+            .get(&MetadataCache::key(&alice, "UserViews", ""))
+            .is_none());
+        assert!(cache
+            .get(&MetadataCache::key(&bob, "UserViews", ""))
+            .is_some());
+        assert_eq!(cache.ttl_for(&Method::POST, "UserViews"), None);
+        assert_eq!(cache.ttl_for(&Method::GET, "Items/abc"), None);
+    }
+}
@@ -0,0 +1,326 @@
+//
+//  media-savant-api
+//  jellyfin/mod.rs
+//
+
+mod breaker;
+mod cache;
+mod transport;
+
+use std::fmt;
+use std::time::Instant;
+
+use tracing::warn;
+
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::Instrument;
+
+pub use breaker::CircuitBreakers;
+pub use cache::MetadataCache;
+pub use transport::{Traffic, Transport};
+
+use crate::metrics::method_label;
+use crate::models::{ApiResponse, CircuitState, SessionData};
+use crate::routes::auth::build_token_header;
+use crate::state::AppState;
+use crate::telemetry::{propagate, upstream_span};
+
+/// Sends a request to Jellyfin inside an upstream span, forwarding the request ID and
+/// recording latency and transport failures.
+///
+/// Metadata GETs that fail to connect, time out or get a 502-504 are retried with
+/// exponential backoff, up to `upstream.retries` times. The final outcome, after any retries,
+/// feeds the server's circuit breaker; while it is open, requests fail with `Unavailable`
+/// unsent.
+pub async fn execute(
+    state: &AppState,
+    traffic: Traffic,
+    mut request: reqwest::Request,
+) -> Result<reqwest::Response, JellyfinError> {
+    let config = state.config();
+    let upstream = &config.upstream;
+    let server = breaker::server_for(request.url(), &config);
+    let client = state.transport.client(request.url().as_str(), traffic);
+    let idempotent = matches!(
+        *request.method(),
+        reqwest::Method::GET | reqwest::Method::HEAD
+    );
+    let retries = if traffic == Traffic::Metadata && idempotent {
+        upstream.retries
+    } else {
+        0
+    };
+
+    let method = method_label(request.method().as_str());
+    let mut backoff = upstream.retry_backoff;
+    let mut attempt = 0;
+    let response = loop {
+        let circuit = state.breakers.state(&server);
+        if circuit != CircuitState::Closed {
+            state.metrics.record_upstream_error("unavailable");
+            return Err(JellyfinError::Unavailable {
+                server,
+                waking: circuit == CircuitState::Waking,
+            });
+        }
+        let retry = (attempt < retries).then(|| request.try_clone()).flatten();
+
+        let span = upstream_span(method, request.url().path());
+        span.in_scope(|| propagate(request.headers_mut()));
+        let started = Instant::now();
+        let response = client.execute(request).instrument(span.clone()).await;
+        state.metrics.record_upstream(method, started.elapsed());
+        match &response {
+            Ok(response) => {
+                span.record("status", response.status().as_u16());
+            }
+            Err(_) => state.metrics.record_upstream_error("request"),
+        }
+
+        let Some(next) = retry.filter(|_| is_transient(&response)) else {
+            break response;
+        };
+        attempt += 1;
+        warn!(
+            path = next.url().path(),
+            attempt,
+            "Retrying Jellyfin request in {}ms: {}",
+            backoff.as_millis(),
+            match &response {
+                Ok(response) => response.status().to_string(),
+                Err(err) => err.to_string(),
+            }
+        );
+        state.metrics.record_upstream_retry();
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        request = next;
+    };
+
+    breaker::record(state, &server, &response);
+    response.map_err(JellyfinError::Request)
+}
+
+/// Failures that are likely to go away on their own, such as Jellyfin restarting.
+fn is_transient(response: &reqwest::Result<reqwest::Response>) -> bool {
+    match response {
+        Ok(response) => matches!(response.status().as_u16(), 502..=504),
+        Err(err) => err.is_connect() || err.is_timeout(),
+    }
+}
+
+/// Typed access to the Jellyfin REST API on behalf of a signed-in session.
+pub struct JellyfinClient<'a> {
+    state: &'a AppState,
+    session: &'a SessionData,
+}
+
+#[derive(Debug)]
+pub enum JellyfinError {
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode),
+    Decode(String),
+    /// The server's circuit is open, or it is being woken up.
+    Unavailable { server: String, waking: bool },
+}
+
+impl<'a> JellyfinClient<'a> {
+    pub fn new(state: &'a AppState, session: &'a SessionData) -> Self {
+        Self { state, session }
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.session.user_id
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", self.session.server_url.trim_end_matches('/'), path);
+        let client = self.state.transport.client(&url, Traffic::Metadata);
+        client.request(method, url).header(
+            "X-Emby-Authorization",
+            build_token_header(self.state, self.session),
+        )
+    }
+
+    fn decode_error(&self, err: impl fmt::Display) -> JellyfinError {
+        self.state.metrics.record_upstream_error("decode");
+        JellyfinError::Decode(err.to_string())
+    }
+
+    /// Sends a request for `path`. A successful write that changes data the metadata cache
+    /// may hold, such as played state, drops the user's cached entries.
+    async fn send(
+        &self,
+        path: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, JellyfinError> {
+        let request = request.build().map_err(JellyfinError::Request)?;
+        let invalidates_cache = MetadataCache::invalidated_by(request.method(), path);
+        let response = execute(self.state, Traffic::Metadata, request).await?;
+        if !response.status().is_success() {
+            self.state.metrics.record_upstream_error("status");
+            return Err(JellyfinError::Status(response.status()));
+        }
+        if invalidates_cache {
+            self.state.metadata.invalidate_user(self.session);
+        }
+        Ok(response)
+    }
+
+    /// GETs and decodes JSON, serving cacheable metadata from the per-user cache.
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, JellyfinError> {
+        let cache = &self.state.metadata;
+        let request = self.request(reqwest::Method::GET, path).query(query);
+        let cached = cache.ttl_for(&reqwest::Method::GET, path).and_then(|ttl| {
+            // Keyed on the encoded query string, so values containing `&` or `=` can't collide,
+            // and normalized the same way as the proxy's keys.
+            let url = request.try_clone()?.build().ok()?.url().clone();
+            let key = MetadataCache::key(self.session, path, url.query().unwrap_or(""));
+            Some((key, ttl))
+        });
+        if let Some(hit) = cached.as_ref().and_then(|(key, _)| cache.get(key)) {
+            return serde_json::from_slice(&hit.body)
+                .map_err(|err| self.decode_error(err));
+        }
+
+        let response = self.send(path, request).await?;
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| self.decode_error(err))?;
+        let decoded =
+            serde_json::from_slice(&body).map_err(|err| self.decode_error(err))?;
+
+        if let Some((key, ttl)) = cached {
+            cache.put(key, ttl, body, Some("application/json".to_string()));
+        }
+        Ok(decoded)
+    }
+
+    pub async fn get_text(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<String, JellyfinError> {
+        let response = self
+            .send(path, self.request(reqwest::Method::GET, path).query(query))
+            .await?;
+        response
+            .text()
+            .await
+            .map_err(|err| self.decode_error(err))
+    }
+
+    /// POSTs a command that Jellyfin answers with an empty body.
+    pub async fn post(&self, path: &str, query: &[(&str, String)]) -> Result<(), JellyfinError> {
+        self.send(path, self.request(reqwest::Method::POST, path).query(query))
+            .await?;
+        Ok(())
+    }
+
+    /// POSTs a JSON body, ignoring whatever Jellyfin responds with.
+    pub async fn post_json<B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        body: &B,
+    ) -> Result<(), JellyfinError> {
+        self.send(
+            path,
+            self.request(reqwest::Method::POST, path)
+                .query(query)
+                .json(body),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Fetches a binary resource such as artwork, returning it with its content type.
+    pub async fn get_bytes(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<(Bytes, Option<String>), JellyfinError> {
+        let response = self
+            .send(path, self.request(reqwest::Method::GET, path).query(query))
+            .await?;
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|val| val.to_str().ok())
+            .map(|val| val.to_string());
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| self.decode_error(err))?;
+        Ok((bytes, content_type))
+    }
+}
+
+/// Converts a Jellyfin server URL to its WebSocket equivalent, joined with `path`.
+pub fn websocket_url(server_url: &str, path: &str) -> String {
+    let base = server_url.trim_end_matches('/');
+    let base = if let Some(rest) = base.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else if let Some(rest) = base.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else {
+        base.to_string()
+    };
+    format!("{base}/{}", path.trim_start_matches('/'))
+}
+
+impl fmt::Display for JellyfinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(err) => write!(f, "Jellyfin request failed: {err}"),
+            Self::Status(status) => write!(f, "Jellyfin returned {status}"),
+            Self::Decode(err) => write!(f, "Invalid Jellyfin response: {err}"),
+            Self::Unavailable {
+                server,
+                waking: true,
+            } => write!(f, "Jellyfin at {server} is waking up"),
+            Self::Unavailable { server, .. } => write!(f, "Jellyfin at {server} is unavailable"),
+        }
+    }
+}
+
+impl std::error::Error for JellyfinError {}
+
+impl ResponseError for JellyfinError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Status(status) if status.as_u16() == 404 => StatusCode::NOT_FOUND,
+            Self::Status(status) if matches!(status.as_u16(), 401 | 403) => {
+                StatusCode::UNAUTHORIZED
+            }
+            Self::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        let body = ApiResponse::<()>::err(self.to_string());
+        match self {
+            Self::Unavailable { waking, .. } => {
+                let code = if *waking {
+                    "jellyfin_waking"
+                } else {
+                    "jellyfin_unavailable"
+                };
+                response
+                    .insert_header(("retry-after", "5"))
+                    .json(body.with_code(code))
+            }
+            _ => response.json(body),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//
+//  media-savant-api
+//  jellyfin/transport.rs
+//
+
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use anyhow::Context;
+use arc_swap::ArcSwap;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring as provider, verify_tls12_signature, verify_tls13_signature};
+use rustls::crypto::{CryptoProvider, WebPkiSupportedAlgorithms};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+
+use crate::config::{Config, JellyfinServerConfig, UpstreamConfig, UpstreamTimeouts};
+
+/// Which timeouts a request to Jellyfin runs under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Traffic {
+    Metadata,
+    Streaming,
+}
+
+impl Traffic {
+    /// Playback, downloads and live TV streams run under the streaming timeouts; everything
+    /// else, artwork included, is metadata.
+    pub fn for_path(path: &str) -> Self {
+        let segments: Vec<String> = path
+            .split('?')
+            .next()
+            .unwrap_or("")
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        match segments.as_slice() {
+            ["videos" | "audio", _, _, ..]
+            | ["items", _, "download" | "file"]
+            | ["livetv", "livestreamfiles" | "liverecordings", ..] => Self::Streaming,
+            _ => Self::Metadata,
+        }
+    }
+}
+
+/// HTTP clients for Jellyfin, rebuilt from the configuration on every reload.
+///
+/// Servers that opt out of CA validation (`insecure_tls` or `pinned_certs`) get clients of
+/// their own; every other URL, including servers users sign in to ad hoc, shares the default.
+pub struct Transport {
+    current: ArcSwap<Clients>,
+}
+
+struct Clients {
+    default: Route,
+    servers: Vec<(String, Route)>,
+}
+
+struct Route {
+    metadata: reqwest::Client,
+    streaming: reqwest::Client,
+    /// Also used for WebSockets, which don't go through reqwest.
+    tls: Arc<ClientConfig>,
+}
+
+impl Transport {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        Ok(Self {
+            current: ArcSwap::from_pointee(Clients::build(config)?),
+        })
+    }
+
+    /// Rebuilds every client, re-reading the CA bundle. Open connections are not reused, so
+    /// new timeouts and trust settings apply to the next request.
+    pub fn reconfigure(&self, config: &Config) -> anyhow::Result<()> {
+        self.current.store(Arc::new(Clients::build(config)?));
+        Ok(())
+    }
+
+    /// The client for requests to `url`.
+    pub fn client(&self, url: &str, traffic: Traffic) -> reqwest::Client {
+        let clients = self.current.load();
+        let route = clients.route(url);
+        match traffic {
+            Traffic::Metadata => route.metadata.clone(),
+            Traffic::Streaming => route.streaming.clone(),
+        }
+    }
+
+    /// TLS settings for WebSocket connections to the server at `url`.
+    pub fn tls_config(&self, url: &str) -> Arc<ClientConfig> {
+        self.current.load().route(url).tls.clone()
+    }
+}
+
+impl Clients {
+    fn build(config: &Config) -> anyhow::Result<Self> {
+        let provider = Arc::new(provider::default_provider());
+        let upstream = &config.upstream;
+
+        let mut roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        if let Some(path) = &upstream.ca_bundle {
+            let certs = CertificateDer::pem_file_iter(path)
+                .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                .with_context(|| format!("Failed to read CA bundle {}", path.display()))?;
+            if certs.is_empty() {
+                anyhow::bail!("No certificates found in CA bundle {}", path.display());
+            }
+            let (_, rejected) = roots.add_parsable_certificates(certs);
+            if rejected > 0 {
+                anyhow::bail!(
+                    "{rejected} certificate(s) in CA bundle {} are not valid CAs",
+                    path.display()
+                );
+            }
+        }
+        let verified = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let servers = config
+            .jellyfin
+            .servers
+            .iter()
+            .filter(|server| server.insecure_tls || !server.pinned_certs.is_empty())
+            .map(|server| {
+                let trust = ServerTrust::new(server, &provider);
+                let tls = ClientConfig::builder_with_provider(provider.clone())
+                    .with_safe_default_protocol_versions()?
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(trust))
+                    .with_no_client_auth();
+                Ok((server.url.clone(), Route::new(tls, upstream)?))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            default: Route::new(verified, upstream)?,
+            servers,
+        })
+    }
+
+    fn route(&self, url: &str) -> &Route {
+        self.servers
+            .iter()
+            .find(|(base, _)| {
+                url.strip_prefix(base.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+            })
+            .map_or(&self.default, |(_, route)| route)
+    }
+}
+
+impl Route {
+    fn new(tls: ClientConfig, upstream: &UpstreamConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            metadata: http_client(&tls, upstream, &upstream.metadata)?,
+            streaming: http_client(&tls, upstream, &upstream.streaming)?,
+            tls: Arc::new(tls),
+        })
+    }
+}
+
+fn http_client(
+    tls: &ClientConfig,
+    upstream: &UpstreamConfig,
+    timeouts: &UpstreamTimeouts,
+) -> anyhow::Result<reqwest::Client> {
+    // reqwest leaves ALPN alone on a preconfigured client; offer HTTP/2 like its default does.
+    let mut tls = tls.clone();
+    tls.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    let mut builder = reqwest::Client::builder()
+        .use_preconfigured_tls(tls)
+        .connect_timeout(upstream.connect_timeout)
+        .read_timeout(timeouts.read);
+    if let Some(total) = timeouts.total {
+        builder = builder.timeout(total);
+    }
+    builder
+        .build()
+        .context("Failed to build the Jellyfin HTTP client")
+}
+
+/// Stands in for CA validation on servers with `insecure_tls` (any certificate) or
+/// `pinned_certs` (only those certificates). The handshake signature is still checked, so
+/// the server has to hold the key for the certificate it presents.
+#[derive(Debug)]
+struct ServerTrust {
+    /// Lowercase hex SHA-256 fingerprints; empty accepts any certificate.
+    pins: Vec<String>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerTrust {
+    fn new(server: &JellyfinServerConfig, provider: &CryptoProvider) -> Self {
+        Self {
+            pins: server.pinned_certs.clone(),
+            algorithms: provider.signature_verification_algorithms,
+        }
+    }
+}
+
+impl ServerCertVerifier for ServerTrust {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.pins.is_empty() || self.pins.contains(&fingerprint(end_entity)) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "certificate does not match any pinned fingerprint".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+fn fingerprint(cert: &CertificateDer<'_>) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, cert.as_ref());
+    digest
+        .as_ref()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
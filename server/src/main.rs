@@ -3,17 +3,32 @@
 //  main.rs
 //
 
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
 use actix_cors::Cors;
-use actix_governor::{Governor, GovernorConfigBuilder};
-use actix_web::middleware::Logger;
+use actix_web::http::KeepAlive;
+use actix_web::middleware::from_fn;
 use actix_web::{web, App, HttpServer};
 use dotenvy::dotenv;
-use log::info;
+use tracing::info;
 
 mod config;
+mod events;
+mod images;
+mod jellyfin;
+mod metrics;
 mod models;
+mod ratelimit;
+mod redact;
 mod routes;
+mod shutdown;
 mod state;
+mod subtitles;
+mod telemetry;
+mod tls;
+mod wol;
 
 use crate::config::Config;
 use crate::state::AppState;
@@ -21,51 +36,229 @@ use crate::state::AppState;
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
 
-    info!("Booting media-savant-api...");
+    let args: Vec<String> = std::env::args().collect();
+
+    // `--config <path>` wins over `CONFIG_FILE`; only the default location may be missing.
+    let explicit_path = args
+        .iter()
+        .position(|arg| arg == "--config")
+        .and_then(|index| args.get(index + 1).cloned())
+        .or_else(|| std::env::var("CONFIG_FILE").ok().filter(|path| !path.is_empty()));
+    let config_path = PathBuf::from(
+        explicit_path
+            .clone()
+            .unwrap_or_else(|| config::DEFAULT_CONFIG_FILE.to_string()),
+    );
+    let loaded = Config::load(&config_path, explicit_path.is_some());
+
+    if args.iter().any(|arg| arg == "--health-check") {
+        std::process::exit(match &loaded {
+            Ok(config) => probe_readiness(config).await,
+            Err(_) => 1,
+        });
+    }
+
+    if args.iter().any(|arg| arg == "--check-config") {
+        match loaded {
+            Ok(config) => {
+                match &config.file {
+                    Some(file) => println!("Configuration OK ({})", file.display()),
+                    None => println!("Configuration OK (environment only)"),
+                }
+                println!("{config:#?}");
+                std::process::exit(0);
+            }
+            Err(errors) => {
+                eprintln!("Configuration has {} problem(s):", errors.0.len());
+                eprintln!("{}", redact::sanitize(&errors.to_string()));
+                std::process::exit(1);
+            }
+        }
+    }
 
-    let config = match Config::from_env() {
+    let config = match loaded {
         Ok(cfg) => cfg,
+        Err(errors) => {
+            eprintln!("Failed to load configuration:");
+            eprintln!("{}", redact::sanitize(&errors.to_string()));
+            std::process::exit(1);
+        }
+    };
+
+    let telemetry = match telemetry::init(&config.telemetry) {
+        Ok(telemetry) => telemetry,
         Err(err) => {
-            eprintln!("Failed to load configuration: {err}");
+            eprintln!("Failed to initialize telemetry: {err}");
             std::process::exit(1);
         }
     };
 
+    info!("Booting {}...", config.telemetry.service_name);
+    if let Some(file) = &config.file {
+        info!("Loaded configuration from {}", file.display());
+    }
+
     let app_state = match AppState::new(config.clone()).await {
         Ok(state) => web::Data::new(state),
         Err(err) => {
-            eprintln!("Failed to initialize app state: {err}");
+            eprintln!("Failed to initialize app state: {}", redact::sanitize(&err.to_string()));
             std::process::exit(1);
         }
     };
 
-    let addr = format!("0.0.0.0:{}", config.app.port);
-    info!("Listening on {addr}");
+    config::watch(app_state.clone(), config_path, explicit_path.is_some());
+    ratelimit::prune_periodically(app_state.clone());
+
+    let tls = match app_state.certs.as_ref().map(|certs| certs.server_config()) {
+        Some(Ok(tls)) => Some(tls),
+        Some(Err(err)) => {
+            eprintln!("Failed to configure TLS: {err:#}");
+            std::process::exit(1);
+        }
+        None => None,
+    };
 
-    HttpServer::new(move || {
+    let tuning = config.server.clone();
+    let shutdown_state = app_state.clone();
+    let mut server = HttpServer::new(move || {
+        // Consults the live config, so reloaded origins apply without rebuilding the app.
+        let origins = app_state.clone();
         let cors = Cors::default()
-            .allow_any_origin()
+            .allowed_origin_fn(move |origin, _| {
+                origin
+                    .to_str()
+                    .is_ok_and(|origin| origins.config().cors.allows(origin))
+            })
             .allow_any_method()
             .allow_any_header()
+            .expose_headers([telemetry::REQUEST_ID_HEADER])
             .supports_credentials();
 
-        let governor = GovernorConfigBuilder::default()
-            .per_second(config.rate_limit.per_second)
-            .burst_size(config.rate_limit.burst)
-            .finish()
-            .unwrap();
-
         App::new()
             .app_data(app_state.clone())
+            .app_data(web::JsonConfig::default().limit(tuning.json_limit_bytes))
+            // Only the Jellyfin proxy reads raw bodies, so this bounds proxied uploads.
+            .app_data(web::PayloadConfig::new(tuning.upload_limit_bytes))
             .wrap(cors)
-            .wrap(Governor::new(&governor))
-            .wrap(Logger::default())
+            .wrap(from_fn(ratelimit::limit_requests))
+            .wrap(from_fn(metrics::track_requests))
+            .wrap(from_fn(tls::redirect_to_https))
+            .wrap(from_fn(telemetry::trace_requests))
             .configure(routes::init)
     })
-    .bind(addr.as_str())?
-    .workers(2)
-    .run()
-    .await
+    .workers(config.server.workers)
+    .keep_alive(if config.server.keep_alive.is_zero() {
+        KeepAlive::Disabled
+    } else {
+        KeepAlive::Timeout(config.server.keep_alive)
+    })
+    .client_request_timeout(config.server.client_request_timeout)
+    .client_disconnect_timeout(config.server.client_disconnect_timeout)
+    .max_connections(config.server.max_connections)
+    // SIGTERM and Ctrl-C are handled by `shutdown::on_signal`, which drains streams first.
+    .disable_signals()
+    // After a clean drain, other requests get this long to finish; long-lived WebSockets and
+    // event streams are closed when it runs out.
+    .shutdown_timeout(shutdown::REQUEST_DRAIN_SECS);
+
+    let redirect_port = config.tls.as_ref().and_then(|tls| tls.redirect_port);
+    let bind_error = |addr: SocketAddr| {
+        move |err: std::io::Error| {
+            std::io::Error::new(err.kind(), format!("Failed to bind {addr}: {err}"))
+        }
+    };
+
+    for &addr in &config.server.binds {
+        match &tls {
+            Some(tls) => {
+                server = server
+                    .bind_rustls_0_23(addr, tls.clone())
+                    .map_err(bind_error(addr))?;
+                info!("Listening on https://{addr}");
+            }
+            None => {
+                server = server.bind(addr).map_err(bind_error(addr))?;
+                info!("Listening on {addr}");
+            }
+        }
+    }
+    let redirects = redirect_addrs(&config.server.binds, redirect_port);
+    let ipv6_wildcard = redirects
+        .iter()
+        .any(|addr| addr.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+    for &addr in &redirects {
+        match std::net::TcpListener::bind(addr) {
+            Ok(listener) => {
+                server = server.listen(listener).map_err(bind_error(addr))?;
+                info!("Redirecting http://{addr} to HTTPS");
+            }
+            // A dual-stack `[::]` listener, bound first, already accepts IPv4 connections.
+            Err(err)
+                if err.kind() == std::io::ErrorKind::AddrInUse
+                    && ipv6_wildcard
+                    && addr.ip() == IpAddr::V4(Ipv4Addr::UNSPECIFIED) =>
+            {
+                info!("Redirecting http://{addr} to HTTPS through the IPv6 listener");
+            }
+            Err(err) => return Err(bind_error(addr)(err)),
+        }
+    }
+    let server = server.run();
+
+    actix_web::rt::spawn(shutdown::on_signal(shutdown_state, server.handle()));
+    let result = server.await;
+
+    info!("Shutdown complete");
+    telemetry.shutdown();
+    result
+}
+
+/// One redirect address per bound IP, since several binds may share an address. The IPv6
+/// wildcard comes first, so that on dual-stack hosts the IPv4 wildcard is the one found taken.
+fn redirect_addrs(binds: &[SocketAddr], port: Option<u16>) -> Vec<SocketAddr> {
+    let Some(port) = port else {
+        return Vec::new();
+    };
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+    for bind in binds {
+        let addr = SocketAddr::new(bind.ip(), port);
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    addrs.sort_by_key(|addr| addr.ip() != IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+    addrs
+}
+
+/// Exit code for container health checks: 0 once `/api/health/ready` answers with a 2xx.
+async fn probe_readiness(config: &Config) -> i32 {
+    let Some(mut addr) = config.server.binds.first().copied() else {
+        return 1;
+    };
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr.ip() {
+            IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+            IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+    let scheme = if config.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    let url = format!("{scheme}://{addr}/api/health/ready");
+
+    // The certificate names the public host, not the loopback address probed here.
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build();
+    let response = match client {
+        Ok(client) => client.get(url).timeout(Duration::from_secs(5)).send().await,
+        Err(_) => return 1,
+    };
+    match response {
+        Ok(response) if response.status().is_success() => 0,
+        _ => 1,
+    }
 }
@@ -0,0 +1,290 @@
+//
+//  media-savant-api
+//  metrics/mod.rs
+//
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+
+use crate::state::AppState;
+
+/// Latency buckets in seconds, from cache hits up to slow transcoder starts.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Prometheus metrics for the API, its Jellyfin upstreams and Redis.
+///
+/// Every label is drawn from a small fixed set (route templates, methods, status classes),
+/// never from raw paths or ids.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    rate_limited: IntCounter,
+    upstream_duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    upstream_retries: IntCounter,
+    circuit_trips: IntCounter,
+    open_circuits: IntGauge,
+    wake_packets: IntCounter,
+    redis_duration: HistogramVec,
+    active_streams: IntGauge,
+    streamed_bytes: IntCounter,
+    sessions: IntGauge,
+    /// When sessions were last counted; scrapes in between reuse the gauge.
+    sessions_counted: Mutex<Option<Instant>>,
+}
+
+impl Metrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("media_savant".to_string()), None)?;
+
+        let http_requests = IntCounterVec::new(
+            Opts::new(
+                "http_requests_total",
+                "Requests handled, by route and status class",
+            ),
+            &["route", "method", "status"],
+        )?;
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time to produce a response",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["route", "method"],
+        )?;
+        let rate_limited = IntCounter::new(
+            "rate_limited_requests_total",
+            "Requests rejected by the rate limiter",
+        )?;
+        let upstream_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "jellyfin_request_duration_seconds",
+                "Time until Jellyfin responded with headers",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["method"],
+        )?;
+        let upstream_errors = IntCounterVec::new(
+            Opts::new(
+                "jellyfin_errors_total",
+                "Failed Jellyfin requests, by failure kind",
+            ),
+            &["kind"],
+        )?;
+        let upstream_retries = IntCounter::new(
+            "jellyfin_retries_total",
+            "Jellyfin requests retried after a transient failure",
+        )?;
+        let circuit_trips = IntCounter::new(
+            "jellyfin_circuit_trips_total",
+            "Times a Jellyfin server's circuit breaker opened",
+        )?;
+        let open_circuits = IntGauge::new(
+            "jellyfin_open_circuits",
+            "Jellyfin servers currently failed fast by the circuit breaker, waking ones included",
+        )?;
+        let wake_packets = IntCounter::new(
+            "jellyfin_wake_packets_total",
+            "Wake-on-LAN packets sent to sleeping Jellyfin servers",
+        )?;
+        let redis_duration = HistogramVec::new(
+            HistogramOpts::new("redis_command_duration_seconds", "Redis round trip time")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["command"],
+        )?;
+        let active_streams = IntGauge::new("active_streams", "Video streams being relayed")?;
+        let streamed_bytes =
+            IntCounter::new("streamed_bytes_total", "Video bytes relayed to clients")?;
+        let sessions = IntGauge::new("sessions", "Signed-in sessions stored in Redis")?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_duration.clone()))?;
+        registry.register(Box::new(rate_limited.clone()))?;
+        registry.register(Box::new(upstream_duration.clone()))?;
+        registry.register(Box::new(upstream_errors.clone()))?;
+        registry.register(Box::new(upstream_retries.clone()))?;
+        registry.register(Box::new(circuit_trips.clone()))?;
+        registry.register(Box::new(open_circuits.clone()))?;
+        registry.register(Box::new(wake_packets.clone()))?;
+        registry.register(Box::new(redis_duration.clone()))?;
+        registry.register(Box::new(active_streams.clone()))?;
+        registry.register(Box::new(streamed_bytes.clone()))?;
+        registry.register(Box::new(sessions.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_duration,
+            rate_limited,
+            upstream_duration,
+            upstream_errors,
+            upstream_retries,
+            circuit_trips,
+            open_circuits,
+            wake_packets,
+            redis_duration,
+            active_streams,
+            streamed_bytes,
+            sessions,
+            sessions_counted: Mutex::new(None),
+        })
+    }
+
+    /// Renders every metric in the Prometheus text format.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+
+    pub fn record_rate_limited(&self) {
+        self.rate_limited.inc();
+    }
+
+    pub fn record_upstream(&self, method: &str, elapsed: Duration) {
+        self.upstream_duration
+            .with_label_values(&[method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Counts a failed Jellyfin call; `kind` is `request`, `status`, `decode` or `unavailable`
+    /// (refused by an open circuit).
+    pub fn record_upstream_error(&self, kind: &str) {
+        self.upstream_errors.with_label_values(&[kind]).inc();
+    }
+
+    pub fn record_upstream_retry(&self) {
+        self.upstream_retries.inc();
+    }
+
+    pub fn record_circuit_opened(&self) {
+        self.circuit_trips.inc();
+    }
+
+    pub fn set_open_circuits(&self, count: usize) {
+        self.open_circuits.set(count as i64);
+    }
+
+    pub fn record_wake_sent(&self) {
+        self.wake_packets.inc();
+    }
+
+    /// Times a Redis command until the returned timer is dropped.
+    pub fn redis_timer(&self, command: &str) -> HistogramTimer {
+        self.redis_duration
+            .with_label_values(&[command])
+            .start_timer()
+    }
+
+    pub fn set_sessions(&self, count: i64) {
+        self.sessions.set(count);
+    }
+
+    /// Whether the session count is older than `refresh`. A `true` claims the recount, so
+    /// concurrent scrapes don't each scan Redis.
+    pub fn claim_session_count(&self, refresh: Duration) -> bool {
+        let mut counted = self.sessions_counted.lock().unwrap();
+        if counted.is_some_and(|at| at.elapsed() < refresh) {
+            return false;
+        }
+        *counted = Some(Instant::now());
+        true
+    }
+
+    /// Marks a stream as active until the returned guard is dropped.
+    pub fn stream_started(self: &Arc<Self>) -> StreamGuard {
+        self.active_streams.inc();
+        StreamGuard {
+            metrics: self.clone(),
+        }
+    }
+}
+
+/// Held for the lifetime of a relayed stream.
+pub struct StreamGuard {
+    metrics: Arc<Metrics>,
+}
+
+impl StreamGuard {
+    pub fn add_bytes(&self, bytes: usize) {
+        self.metrics.streamed_bytes.inc_by(bytes as u64);
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.metrics.active_streams.dec();
+    }
+}
+
+/// Records count, latency and status class for every request, labelled by route template.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let Some(metrics) = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.metrics.clone())
+    else {
+        return next.call(req).await;
+    };
+
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = method_label(req.method().as_str());
+    let started = Instant::now();
+
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(response) => response.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+
+    metrics
+        .http_requests
+        .with_label_values(&[&route, method, status_class(status)])
+        .inc();
+    metrics
+        .http_duration
+        .with_label_values(&[&route, method])
+        .observe(started.elapsed().as_secs_f64());
+    result
+}
+
+/// Standard methods keep their name; anything else shares one label.
+pub fn method_label(method: &str) -> &'static str {
+    match method {
+        "GET" => "GET",
+        "HEAD" => "HEAD",
+        "POST" => "POST",
+        "PUT" => "PUT",
+        "PATCH" => "PATCH",
+        "DELETE" => "DELETE",
+        "OPTIONS" => "OPTIONS",
+        _ => "OTHER",
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
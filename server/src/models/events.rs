@@ -0,0 +1,38 @@
+//
+//  media-savant-api
+//  models/events.rs
+//
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A message pushed to browsers over `/api/events`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerEvent {
+    /// Jellyfin `MessageType`, or `Connection` for the relay's own status changes.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub data: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// Comma separated event types to receive; everything is sent when omitted.
+    pub types: Option<String>,
+}
+
+impl ServerEvent {
+    pub fn connection(state: &str) -> Self {
+        Self {
+            kind: "Connection".to_string(),
+            data: serde_json::json!({ "state": state }),
+        }
+    }
+
+    /// Formats the event as a server-sent event frame.
+    pub fn to_sse(&self) -> String {
+        let data = serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string());
+        format!("event: {}\ndata: {data}\n\n", self.kind)
+    }
+}
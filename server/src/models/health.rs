@@ -0,0 +1,122 @@
+//
+//  media-savant-api
+//  models/health.rs
+//
+
+use serde::Serialize;
+
+use crate::redact::sanitize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Up,
+    /// Serving, but something users will notice is wrong.
+    Degraded,
+    /// Cannot serve requests; readiness answers `503`.
+    Down,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Liveness {
+    pub status: HealthStatus,
+    pub uptime_secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Readiness {
+    pub status: HealthStatus,
+    /// Set once a stop signal arrived; the instance should be taken out of rotation.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub draining: bool,
+    pub session_store: ComponentHealth,
+    pub jellyfin: Vec<JellyfinServerHealth>,
+    pub config: ConfigHealth,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JellyfinServerHealth {
+    pub url: String,
+    #[serde(flatten)]
+    pub health: ComponentHealth,
+    pub circuit: CircuitState,
+}
+
+/// Whether requests to a Jellyfin server are let through or failed straight away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    /// Recent requests failed; callers get `jellyfin_unavailable` until a ping succeeds.
+    Open,
+    /// A Wake-on-LAN packet was sent; callers get `jellyfin_waking` until the server answers.
+    Waking,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigHealth {
+    pub status: HealthStatus,
+    pub reload_pending: bool,
+    /// Why the last reload was rejected; the previous settings are still in effect.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Changed settings that only take effect after a restart.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub restart_required: Vec<&'static str>,
+}
+
+impl ComponentHealth {
+    pub fn up(latency_ms: u64) -> Self {
+        Self {
+            status: HealthStatus::Up,
+            latency_ms: Some(latency_ms),
+            error: None,
+        }
+    }
+
+    pub fn down(error: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Down,
+            latency_ms: None,
+            error: Some(sanitize(&error.into())),
+        }
+    }
+}
+
+impl Readiness {
+    /// Only the session store is fatal: without it no request can be authenticated. Unreachable
+    /// Jellyfin servers or a pending or rejected reload degrade the service but keep it in
+    /// rotation. A draining instance is down so load balancers stop sending it new work.
+    pub fn overall(
+        draining: bool,
+        session_store: &ComponentHealth,
+        jellyfin: &[JellyfinServerHealth],
+        config: &ConfigHealth,
+    ) -> HealthStatus {
+        if draining || session_store.status == HealthStatus::Down {
+            HealthStatus::Down
+        } else if config.status != HealthStatus::Up
+            || jellyfin
+                .iter()
+                .any(|server| server.health.status != HealthStatus::Up)
+        {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Up
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//
+//  media-savant-api
+//  models/home.rs
+//
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{LibraryView, MediaItem};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HomeFeed {
+    pub continue_watching: Vec<MediaItem>,
+    pub next_up: Vec<MediaItem>,
+    pub recently_added: Vec<LatestSection>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestSection {
+    pub library: LibraryView,
+    pub items: Vec<MediaItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HomeQuery {
+    /// Maximum items per row.
+    pub limit: Option<u32>,
+}
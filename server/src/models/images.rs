@@ -0,0 +1,64 @@
+//
+//  media-savant-api
+//  models/images.rs
+//
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImageQuery {
+    /// Jellyfin image tag; tagged images are immutable and cached indefinitely.
+    pub tag: Option<String>,
+    pub index: Option<u32>,
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub fill_width: Option<u32>,
+    pub fill_height: Option<u32>,
+    pub quality: Option<u32>,
+}
+
+impl ImageQuery {
+    /// Jellyfin query parameters for this rendition.
+    pub fn upstream_params(&self) -> Vec<(&'static str, String)> {
+        [
+            ("tag", self.tag.clone()),
+            ("maxWidth", self.max_width.map(|v| v.to_string())),
+            ("maxHeight", self.max_height.map(|v| v.to_string())),
+            ("fillWidth", self.fill_width.map(|v| v.to_string())),
+            ("fillHeight", self.fill_height.map(|v| v.to_string())),
+            ("quality", self.quality.map(|v| v.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaceholderFormat {
+    #[default]
+    Png,
+    /// JSON carrying the hash, its average colour and a `data:` URI of the PNG.
+    DataUri,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaceholderQuery {
+    pub tag: Option<String>,
+    pub index: Option<u32>,
+    /// BlurHash to render; computed from the artwork itself when omitted.
+    pub hash: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub format: PlaceholderFormat,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaceholderData {
+    pub blur_hash: String,
+    pub average_color: Option<String>,
+    pub data_uri: String,
+}
@@ -0,0 +1,227 @@
+//
+//  media-savant-api
+//  models/jellyfin.rs
+//
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinPlaybackInfo {
+    #[serde(default)]
+    pub media_sources: Vec<JellyfinMediaSource>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinMediaSource {
+    pub id: String,
+    #[serde(default)]
+    pub media_streams: Vec<JellyfinMediaStream>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinMediaStream {
+    pub index: i32,
+    #[serde(rename = "Type")]
+    pub stream_type: String,
+    pub codec: Option<String>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub display_title: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(default)]
+    pub is_forced: bool,
+    #[serde(default)]
+    pub is_external: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinItemsResponse {
+    #[serde(default)]
+    pub items: Vec<JellyfinItem>,
+    #[serde(default)]
+    pub total_record_count: u32,
+    #[serde(default)]
+    pub start_index: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinItem {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "Type", default)]
+    pub item_type: String,
+    pub collection_type: Option<String>,
+    pub parent_id: Option<String>,
+    pub series_id: Option<String>,
+    pub series_name: Option<String>,
+    pub season_id: Option<String>,
+    pub season_name: Option<String>,
+    pub index_number: Option<i32>,
+    pub parent_index_number: Option<i32>,
+    pub production_year: Option<i32>,
+    pub overview: Option<String>,
+    pub run_time_ticks: Option<i64>,
+    pub official_rating: Option<String>,
+    pub community_rating: Option<f32>,
+    pub premiere_date: Option<String>,
+    pub date_created: Option<String>,
+    pub child_count: Option<u32>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub taglines: Vec<String>,
+    #[serde(default)]
+    pub studios: Vec<JellyfinNameId>,
+    #[serde(default)]
+    pub people: Vec<JellyfinPerson>,
+    #[serde(default)]
+    pub image_tags: HashMap<String, String>,
+    #[serde(default)]
+    pub backdrop_image_tags: Vec<String>,
+    pub parent_backdrop_item_id: Option<String>,
+    #[serde(default)]
+    pub parent_backdrop_image_tags: Vec<String>,
+    pub series_primary_image_tag: Option<String>,
+    /// BlurHashes keyed by image type, then by image tag.
+    #[serde(default)]
+    pub image_blur_hashes: HashMap<String, HashMap<String, String>>,
+    pub user_data: Option<JellyfinUserData>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinNameId {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinPerson {
+    pub id: Option<String>,
+    pub name: String,
+    pub role: Option<String>,
+    #[serde(rename = "Type")]
+    pub person_type: Option<String>,
+    pub primary_image_tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinUserData {
+    #[serde(default)]
+    pub played: bool,
+    #[serde(default)]
+    pub is_favorite: bool,
+    pub played_percentage: Option<f64>,
+    #[serde(default)]
+    pub playback_position_ticks: i64,
+    pub unplayed_item_count: Option<u32>,
+    pub last_played_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinSearchHints {
+    #[serde(default)]
+    pub search_hints: Vec<JellyfinSearchHint>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinSearchHint {
+    #[serde(alias = "ItemId")]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "Type", default)]
+    pub item_type: String,
+    pub production_year: Option<i32>,
+    pub series: Option<String>,
+    pub index_number: Option<i32>,
+    pub parent_index_number: Option<i32>,
+    pub primary_image_tag: Option<String>,
+    pub thumb_image_tag: Option<String>,
+    pub thumb_image_item_id: Option<String>,
+    pub backdrop_image_tag: Option<String>,
+    pub backdrop_image_item_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinSessionInfo {
+    pub id: String,
+    pub user_name: Option<String>,
+    pub client: Option<String>,
+    pub device_id: Option<String>,
+    pub device_name: Option<String>,
+    pub application_version: Option<String>,
+    pub last_activity_date: Option<String>,
+    #[serde(default)]
+    pub supports_remote_control: bool,
+    #[serde(default)]
+    pub supported_commands: Vec<String>,
+    #[serde(default)]
+    pub playable_media_types: Vec<String>,
+    pub now_playing_item: Option<JellyfinItem>,
+    pub play_state: Option<JellyfinPlayState>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinPlayState {
+    pub position_ticks: Option<i64>,
+    #[serde(default)]
+    pub can_seek: bool,
+    #[serde(default)]
+    pub is_paused: bool,
+    #[serde(default)]
+    pub is_muted: bool,
+    pub volume_level: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinSyncPlayGroup {
+    pub group_id: String,
+    pub group_name: String,
+    pub state: String,
+    #[serde(default)]
+    pub participants: Vec<String>,
+    pub last_updated_at: Option<String>,
+}
+
+/// The parts of a client's `POST /Sessions/Playing[/Progress]` body kept for stop reports.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinPlaybackProgressInfo {
+    pub item_id: String,
+    pub media_source_id: Option<String>,
+    pub position_ticks: Option<i64>,
+    pub play_session_id: Option<String>,
+}
+
+/// Body of `POST /Sessions/Playing/Stopped`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JellyfinPlaybackStopInfo {
+    pub item_id: String,
+    pub media_source_id: String,
+    /// Without it Jellyfin assumes the item was watched to the end.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_ticks: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub play_session_id: Option<String>,
+    /// Ends the session without touching played state or the resume point.
+    pub failed: bool,
+}
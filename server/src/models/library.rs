@@ -0,0 +1,348 @@
+//
+//  media-savant-api
+//  models/library.rs
+//
+
+use serde::{Deserialize, Serialize};
+
+use crate::images::average_color;
+use crate::models::{JellyfinItem, JellyfinItemsResponse, JellyfinPerson, JellyfinUserData};
+
+/// Builds an artwork URL routed through this API.
+pub fn image_url(item_id: &str, image_type: &str, tag: &str) -> String {
+    format!("/api/images/{item_id}/{image_type}?tag={tag}")
+}
+
+/// Builds the URL of a rendered BlurHash placeholder for one piece of artwork.
+pub fn placeholder_url(item_id: &str, image_type: &str, tag: &str, blur_hash: &str) -> String {
+    // BlurHash's base83 alphabet includes URL delimiters such as `#`, `&` and `?`.
+    let hash: String = blur_hash
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() {
+                (byte as char).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect();
+    format!("/api/images/{item_id}/{image_type}/placeholder?tag={tag}&hash={hash}")
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemImages {
+    pub primary: Option<String>,
+    pub backdrop: Option<String>,
+    pub thumb: Option<String>,
+    pub logo: Option<String>,
+    pub primary_placeholder: Option<ImagePlaceholder>,
+    pub backdrop_placeholder: Option<ImagePlaceholder>,
+}
+
+/// Low quality stand-in painted while the real artwork loads.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImagePlaceholder {
+    pub blur_hash: String,
+    /// `#rrggbb`, for clients that only want a solid fill.
+    pub average_color: Option<String>,
+    /// Server rendered PNG of the BlurHash.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserItemData {
+    pub played: bool,
+    pub is_favorite: bool,
+    pub played_percentage: Option<f64>,
+    pub playback_position_ticks: i64,
+    pub unplayed_item_count: Option<u32>,
+    pub last_played_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryView {
+    pub id: String,
+    pub name: String,
+    pub collection_type: Option<String>,
+    pub images: ItemImages,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaItem {
+    pub id: String,
+    pub name: String,
+    pub item_type: String,
+    pub parent_id: Option<String>,
+    pub series_id: Option<String>,
+    pub series_name: Option<String>,
+    pub season_id: Option<String>,
+    pub season_name: Option<String>,
+    pub index_number: Option<i32>,
+    pub parent_index_number: Option<i32>,
+    pub production_year: Option<i32>,
+    pub overview: Option<String>,
+    pub run_time_ticks: Option<i64>,
+    pub official_rating: Option<String>,
+    pub community_rating: Option<f32>,
+    pub premiere_date: Option<String>,
+    pub date_created: Option<String>,
+    pub child_count: Option<u32>,
+    pub genres: Vec<String>,
+    pub images: ItemImages,
+    pub user_data: Option<UserItemData>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    pub id: Option<String>,
+    pub name: String,
+    pub role: Option<String>,
+    pub person_type: Option<String>,
+    pub image_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaItemDetail {
+    #[serde(flatten)]
+    pub item: MediaItem,
+    pub taglines: Vec<String>,
+    pub tags: Vec<String>,
+    pub studios: Vec<String>,
+    pub people: Vec<Person>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemPage {
+    pub items: Vec<MediaItem>,
+    pub total_record_count: u32,
+    pub start_index: u32,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Name,
+    DateAdded,
+    ReleaseDate,
+    Year,
+    Rating,
+    Runtime,
+    LastPlayed,
+    Random,
+}
+
+impl SortField {
+    pub fn as_jellyfin(self) -> &'static str {
+        match self {
+            Self::Name => "SortName",
+            Self::DateAdded => "DateCreated,SortName",
+            Self::ReleaseDate => "PremiereDate,SortName",
+            Self::Year => "ProductionYear,SortName",
+            Self::Rating => "CommunityRating,SortName",
+            Self::Runtime => "Runtime,SortName",
+            Self::LastPlayed => "DatePlayed,SortName",
+            Self::Random => "Random",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_jellyfin(self) -> &'static str {
+        match self {
+            Self::Asc => "Ascending",
+            Self::Desc => "Descending",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LibraryItemsQuery {
+    pub start_index: Option<u32>,
+    pub limit: Option<u32>,
+    pub sort_by: Option<SortField>,
+    pub sort_order: Option<SortOrder>,
+    /// Comma separated genre names.
+    pub genres: Option<String>,
+    /// Comma separated production years.
+    pub years: Option<String>,
+    pub played: Option<bool>,
+    /// Comma separated Jellyfin item types, defaults to `Movie,Series`.
+    pub item_types: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EpisodesQuery {
+    pub season_id: Option<String>,
+    pub start_index: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SimilarItemsQuery {
+    pub limit: Option<u32>,
+}
+
+impl From<JellyfinUserData> for UserItemData {
+    fn from(data: JellyfinUserData) -> Self {
+        Self {
+            played: data.played,
+            is_favorite: data.is_favorite,
+            played_percentage: data.played_percentage,
+            playback_position_ticks: data.playback_position_ticks,
+            unplayed_item_count: data.unplayed_item_count,
+            last_played_date: data.last_played_date,
+        }
+    }
+}
+
+impl From<&JellyfinItem> for ItemImages {
+    fn from(item: &JellyfinItem) -> Self {
+        let tagged = |image_type: &'static str| {
+            item.image_tags
+                .get(image_type)
+                .map(|tag| (item.id.as_str(), image_type, tag.as_str()))
+        };
+
+        let backdrop = item
+            .backdrop_image_tags
+            .first()
+            .map(|tag| (item.id.as_str(), "Backdrop", tag.as_str()))
+            .or_else(|| {
+                let parent = item.parent_backdrop_item_id.as_deref()?;
+                let tag = item.parent_backdrop_image_tags.first()?;
+                Some((parent, "Backdrop", tag.as_str()))
+            });
+
+        // Episodes and seasons without artwork of their own fall back to the series poster.
+        let primary = tagged("Primary").or_else(|| {
+            let series_id = item.series_id.as_deref()?;
+            let tag = item.series_primary_image_tag.as_deref()?;
+            Some((series_id, "Primary", tag))
+        });
+
+        // Jellyfin keys hashes by tag, including those of parent artwork used as a fallback.
+        let placeholder = |(item_id, image_type, tag): (&str, &str, &str)| {
+            let blur_hash = item.image_blur_hashes.get(image_type)?.get(tag)?;
+            Some(ImagePlaceholder {
+                average_color: average_color(blur_hash),
+                url: placeholder_url(item_id, image_type, tag, blur_hash),
+                blur_hash: blur_hash.clone(),
+            })
+        };
+        let url =
+            |(item_id, image_type, tag): (&str, &str, &str)| image_url(item_id, image_type, tag);
+
+        Self {
+            primary_placeholder: primary.and_then(placeholder),
+            backdrop_placeholder: backdrop.and_then(placeholder),
+            primary: primary.map(url),
+            backdrop: backdrop.map(url),
+            thumb: tagged("Thumb").map(url),
+            logo: tagged("Logo").map(url),
+        }
+    }
+}
+
+impl From<JellyfinItem> for LibraryView {
+    fn from(item: JellyfinItem) -> Self {
+        Self {
+            images: ItemImages::from(&item),
+            id: item.id,
+            name: item.name,
+            collection_type: item.collection_type,
+        }
+    }
+}
+
+impl From<JellyfinItem> for MediaItem {
+    fn from(item: JellyfinItem) -> Self {
+        Self {
+            images: ItemImages::from(&item),
+            id: item.id,
+            name: item.name,
+            item_type: item.item_type,
+            parent_id: item.parent_id,
+            series_id: item.series_id,
+            series_name: item.series_name,
+            season_id: item.season_id,
+            season_name: item.season_name,
+            index_number: item.index_number,
+            parent_index_number: item.parent_index_number,
+            production_year: item.production_year,
+            overview: item.overview,
+            run_time_ticks: item.run_time_ticks,
+            official_rating: item.official_rating,
+            community_rating: item.community_rating,
+            premiere_date: item.premiere_date,
+            date_created: item.date_created,
+            child_count: item.child_count,
+            genres: item.genres,
+            user_data: item.user_data.map(UserItemData::from),
+        }
+    }
+}
+
+impl From<JellyfinPerson> for Person {
+    fn from(person: JellyfinPerson) -> Self {
+        let image_url = match (&person.id, &person.primary_image_tag) {
+            (Some(id), Some(tag)) => Some(image_url(id, "Primary", tag)),
+            _ => None,
+        };
+
+        Self {
+            id: person.id,
+            name: person.name,
+            role: person.role,
+            person_type: person.person_type,
+            image_url,
+        }
+    }
+}
+
+impl From<JellyfinItem> for MediaItemDetail {
+    fn from(mut item: JellyfinItem) -> Self {
+        let taglines = std::mem::take(&mut item.taglines);
+        let tags = std::mem::take(&mut item.tags);
+        let studios = std::mem::take(&mut item.studios)
+            .into_iter()
+            .map(|studio| studio.name)
+            .collect();
+        let people = std::mem::take(&mut item.people)
+            .into_iter()
+            .map(Person::from)
+            .collect();
+
+        Self {
+            item: MediaItem::from(item),
+            taglines,
+            tags,
+            studios,
+            people,
+        }
+    }
+}
+
+impl From<JellyfinItemsResponse> for ItemPage {
+    fn from(response: JellyfinItemsResponse) -> Self {
+        Self {
+            total_record_count: response.total_record_count,
+            start_index: response.start_index,
+            items: response.items.into_iter().map(MediaItem::from).collect(),
+        }
+    }
+}
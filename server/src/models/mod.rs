@@ -6,11 +6,44 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::redact::{sanitize, Secret};
+use crate::telemetry::current_request_id;
+
+mod events;
+mod health;
+mod home;
+mod images;
+mod jellyfin;
+mod library;
+mod queue;
+mod remote;
+mod search;
+mod subtitles;
+mod syncplay;
+
+pub use events::*;
+pub use health::*;
+pub use home::*;
+pub use images::*;
+pub use jellyfin::*;
+pub use library::*;
+pub use queue::*;
+pub use remote::*;
+pub use search::*;
+pub use subtitles::*;
+pub use syncplay::*;
+
 #[derive(Debug, Serialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// Machine-readable reason for some errors, e.g. `jellyfin_unavailable`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
+    /// Echoes `X-Request-Id` on errors so users can quote it when reporting problems.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl<T> ApiResponse<T> {
@@ -19,6 +52,8 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             error: None,
+            code: None,
+            request_id: None,
         }
     }
 
@@ -26,7 +61,16 @@ impl<T> ApiResponse<T> {
         Self {
             success: false,
             data: None,
-            error: Some(message.into()),
+            error: Some(sanitize(&message.into())),
+            code: None,
+            request_id: current_request_id(),
+        }
+    }
+
+    pub fn with_code(self, code: &'static str) -> Self {
+        Self {
+            code: Some(code),
+            ..self
         }
     }
 }
@@ -35,7 +79,7 @@ impl<T> ApiResponse<T> {
 pub struct LoginRequest {
     pub server_url: String,
     pub username: String,
-    pub password: String,
+    pub password: Secret<String>,
     pub device_id: Option<String>,
 }
 
@@ -52,30 +96,33 @@ pub struct SessionInfo {
     pub server_url: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionData {
     pub session_id: Uuid,
     pub user_id: String,
     pub username: String,
-    pub access_token: String,
+    pub access_token: Secret<String>,
     pub server_url: String,
     pub device_id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct JellyfinAuthRequest {
-    pub Username: String,
-    pub Pw: String,
+    pub username: String,
+    pub pw: Secret<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct JellyfinUser {
-    pub Id: String,
-    pub Name: String,
+    pub id: String,
+    pub name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct JellyfinAuthResponse {
-    pub User: JellyfinUser,
-    pub AccessToken: String,
+    pub user: JellyfinUser,
+    pub access_token: Secret<String>,
 }
@@ -0,0 +1,372 @@
+//
+//  media-savant-api
+//  models/queue.rs
+//
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex as TokioMutex, OwnedMutexGuard};
+use uuid::Uuid;
+
+use crate::models::{ItemImages, JellyfinItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueSourceType {
+    Item,
+    Season,
+    Series,
+    Playlist,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueEntry {
+    pub entry_id: Uuid,
+    pub item_id: String,
+    pub name: String,
+    pub item_type: String,
+    pub series_name: Option<String>,
+    pub index_number: Option<i32>,
+    pub parent_index_number: Option<i32>,
+    pub run_time_ticks: Option<i64>,
+    pub image_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayQueue {
+    pub id: Uuid,
+    pub source_type: QueueSourceType,
+    pub source_id: String,
+    pub entries: Vec<QueueEntry>,
+    /// Index of the current entry in `entries`.
+    pub position: usize,
+    pub shuffled: bool,
+    /// Entry order before shuffling, restored when shuffle is turned off.
+    pub original_order: Vec<Uuid>,
+    /// Incremented on every change so clients can detect edits from other devices.
+    pub revision: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueueError {
+    EntryNotFound,
+    EndOfQueue,
+    StartOfQueue,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateQueueRequest {
+    pub source_type: QueueSourceType,
+    pub source_id: String,
+    #[serde(default)]
+    pub shuffle: bool,
+    /// Item to start playback from; defaults to the first entry.
+    pub start_item_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShuffleRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderRequest {
+    pub entry_id: Uuid,
+    pub to_index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JumpRequest {
+    pub entry_id: Uuid,
+}
+
+impl From<JellyfinItem> for QueueEntry {
+    fn from(item: JellyfinItem) -> Self {
+        Self {
+            entry_id: Uuid::new_v4(),
+            image_url: ItemImages::from(&item).primary,
+            item_id: item.id,
+            name: item.name,
+            item_type: item.item_type,
+            series_name: item.series_name,
+            index_number: item.index_number,
+            parent_index_number: item.parent_index_number,
+            run_time_ticks: item.run_time_ticks,
+        }
+    }
+}
+
+impl std::fmt::Display for QueueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EntryNotFound => write!(f, "Queue entry not found"),
+            Self::EndOfQueue => write!(f, "Already at the end of the queue"),
+            Self::StartOfQueue => write!(f, "Already at the start of the queue"),
+        }
+    }
+}
+
+impl PlayQueue {
+    pub fn new(request: &CreateQueueRequest, entries: Vec<QueueEntry>) -> Self {
+        let position = request
+            .start_item_id
+            .as_ref()
+            .and_then(|item_id| entries.iter().position(|entry| &entry.item_id == item_id))
+            .unwrap_or(0);
+
+        let mut queue = Self {
+            id: Uuid::new_v4(),
+            source_type: request.source_type,
+            source_id: request.source_id.clone(),
+            original_order: entries.iter().map(|entry| entry.entry_id).collect(),
+            entries,
+            position,
+            shuffled: false,
+            revision: 0,
+        };
+        if request.shuffle {
+            queue.set_shuffle(true);
+        }
+        queue
+    }
+
+    pub fn current(&self) -> Option<&QueueEntry> {
+        self.entries.get(self.position)
+    }
+
+    fn index_of(&self, entry_id: Uuid) -> Result<usize, QueueError> {
+        self.entries
+            .iter()
+            .position(|entry| entry.entry_id == entry_id)
+            .ok_or(QueueError::EntryNotFound)
+    }
+
+    /// Shuffles everything around the current entry, which moves to the front so playback
+    /// continues uninterrupted. Turning shuffle off restores the original order.
+    pub fn set_shuffle(&mut self, enabled: bool) {
+        let current = self.current().map(|entry| entry.entry_id);
+
+        if enabled {
+            let mut rng = rand::thread_rng();
+            self.entries.shuffle(&mut rng);
+            if let Some(index) = current.and_then(|id| self.index_of(id).ok()) {
+                let entry = self.entries.remove(index);
+                self.entries.insert(0, entry);
+            }
+        } else {
+            let order = &self.original_order;
+            self.entries.sort_by_key(|entry| {
+                order
+                    .iter()
+                    .position(|id| *id == entry.entry_id)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+
+        self.shuffled = enabled;
+        self.position = current.and_then(|id| self.index_of(id).ok()).unwrap_or(0);
+    }
+
+    pub fn reorder(&mut self, entry_id: Uuid, to_index: usize) -> Result<(), QueueError> {
+        let from = self.index_of(entry_id)?;
+        let current = self.current().map(|entry| entry.entry_id);
+
+        let entry = self.entries.remove(from);
+        let to_index = to_index.min(self.entries.len());
+        self.entries.insert(to_index, entry);
+
+        if !self.shuffled {
+            self.original_order = self.entries.iter().map(|entry| entry.entry_id).collect();
+        }
+        self.position = current.and_then(|id| self.index_of(id).ok()).unwrap_or(0);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, entry_id: Uuid) -> Result<(), QueueError> {
+        let index = self.index_of(entry_id)?;
+        self.entries.remove(index);
+        self.original_order.retain(|id| *id != entry_id);
+
+        // Removing the current entry makes the following one current.
+        if index < self.position {
+            self.position -= 1;
+        }
+        self.position = self.position.min(self.entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    pub fn jump(&mut self, entry_id: Uuid) -> Result<(), QueueError> {
+        self.position = self.index_of(entry_id)?;
+        Ok(())
+    }
+
+    pub fn next(&mut self) -> Result<(), QueueError> {
+        if self.position + 1 >= self.entries.len() {
+            return Err(QueueError::EndOfQueue);
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    pub fn previous(&mut self) -> Result<(), QueueError> {
+        if self.position == 0 {
+            return Err(QueueError::StartOfQueue);
+        }
+        self.position -= 1;
+        Ok(())
+    }
+}
+
+/// One lock per profile queue, so edits from two devices on the same profile cannot
+/// interleave without making every other Redis user wait for them.
+#[derive(Default)]
+pub struct QueueLocks {
+    locks: Mutex<HashMap<String, Weak<TokioMutex<()>>>>,
+}
+
+impl QueueLocks {
+    pub async fn lock(&self, key: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap();
+            // A lock nobody holds or waits for has no strong references left.
+            locks.retain(|_, lock| lock.strong_count() > 0);
+            match locks.get(key).and_then(Weak::upgrade) {
+                Some(lock) => lock,
+                None => {
+                    let lock = Arc::new(TokioMutex::new(()));
+                    locks.insert(key.to_string(), Arc::downgrade(&lock));
+                    lock
+                }
+            }
+        };
+        lock.lock_owned().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(len: usize, start: usize) -> PlayQueue {
+        let entries: Vec<QueueEntry> = (0..len)
+            .map(|index| QueueEntry {
+                entry_id: Uuid::new_v4(),
+                item_id: format!("item-{index}"),
+                name: format!("Item {index}"),
+                item_type: "Episode".to_string(),
+                series_name: None,
+                index_number: Some(index as i32),
+                parent_index_number: None,
+                run_time_ticks: None,
+                image_url: None,
+            })
+            .collect();
+        let request = CreateQueueRequest {
+            source_type: QueueSourceType::Season,
+            source_id: "season".to_string(),
+            shuffle: false,
+            start_item_id: Some(format!("item-{start}")),
+        };
+        PlayQueue::new(&request, entries)
+    }
+
+    fn item_ids(queue: &PlayQueue) -> Vec<&str> {
+        queue
+            .entries
+            .iter()
+            .map(|entry| entry.item_id.as_str())
+            .collect()
+    }
+
+    fn entry_id(queue: &PlayQueue, item_id: &str) -> Uuid {
+        queue
+            .entries
+            .iter()
+            .find(|entry| entry.item_id == item_id)
+            .unwrap()
+            .entry_id
+    }
+
+    #[test]
+    fn starts_at_requested_item() {
+        let queue = queue(4, 2);
+        assert_eq!(queue.position, 2);
+        assert_eq!(queue.current().unwrap().item_id, "item-2");
+    }
+
+    #[test]
+    fn shuffle_keeps_current_entry_and_restores_order() {
+        let mut queue = queue(20, 5);
+        let before = item_ids(&queue).join(",");
+
+        queue.set_shuffle(true);
+        assert!(queue.shuffled);
+        assert_eq!(queue.position, 0);
+        assert_eq!(queue.current().unwrap().item_id, "item-5");
+        let mut shuffled = item_ids(&queue);
+        shuffled.sort_by_key(|id| id[5..].parse::<usize>().unwrap());
+        assert_eq!(shuffled.join(","), before);
+
+        queue.set_shuffle(false);
+        assert!(!queue.shuffled);
+        assert_eq!(item_ids(&queue).join(","), before);
+        assert_eq!(queue.current().unwrap().item_id, "item-5");
+    }
+
+    #[test]
+    fn reorder_follows_current_entry() {
+        let mut queue = queue(4, 1);
+        queue.reorder(entry_id(&queue, "item-3"), 0).unwrap();
+        assert_eq!(item_ids(&queue), ["item-3", "item-0", "item-1", "item-2"]);
+        assert_eq!(queue.current().unwrap().item_id, "item-1");
+
+        // Out of range indices move the entry to the end.
+        queue.reorder(entry_id(&queue, "item-1"), 99).unwrap();
+        assert_eq!(item_ids(&queue), ["item-3", "item-0", "item-2", "item-1"]);
+        assert_eq!(queue.position, 3);
+
+        // Without shuffle the new order is the one shuffle restores.
+        queue.set_shuffle(true);
+        queue.set_shuffle(false);
+        assert_eq!(item_ids(&queue), ["item-3", "item-0", "item-2", "item-1"]);
+
+        assert_eq!(
+            queue.reorder(Uuid::new_v4(), 0),
+            Err(QueueError::EntryNotFound)
+        );
+    }
+
+    #[test]
+    fn remove_adjusts_position() {
+        let mut queue = queue(4, 2);
+        queue.remove(entry_id(&queue, "item-0")).unwrap();
+        assert_eq!(queue.current().unwrap().item_id, "item-2");
+
+        // Removing the current entry makes the following one current.
+        queue.remove(entry_id(&queue, "item-2")).unwrap();
+        assert_eq!(queue.current().unwrap().item_id, "item-3");
+
+        // ...or the new last one, when it was last.
+        queue.remove(entry_id(&queue, "item-3")).unwrap();
+        assert_eq!(queue.current().unwrap().item_id, "item-1");
+        assert_eq!(queue.original_order.len(), 1);
+
+        assert_eq!(queue.remove(Uuid::new_v4()), Err(QueueError::EntryNotFound));
+    }
+
+    #[test]
+    fn navigation_stops_at_the_ends() {
+        let mut queue = queue(2, 0);
+        assert_eq!(queue.previous(), Err(QueueError::StartOfQueue));
+        queue.next().unwrap();
+        assert_eq!(queue.next(), Err(QueueError::EndOfQueue));
+        queue.jump(entry_id(&queue, "item-0")).unwrap();
+        assert_eq!(queue.position, 0);
+        assert_eq!(queue.jump(Uuid::new_v4()), Err(QueueError::EntryNotFound));
+    }
+}
@@ -0,0 +1,137 @@
+//
+//  media-savant-api
+//  models/remote.rs
+//
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{JellyfinPlayState, JellyfinSessionInfo, MediaItem};
+
+/// Another Jellyfin client of the same user that can be controlled from this one.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSession {
+    pub id: String,
+    pub client: Option<String>,
+    pub device_name: Option<String>,
+    pub application_version: Option<String>,
+    pub user_name: Option<String>,
+    /// Whether this is the session the request was made from.
+    pub is_current: bool,
+    pub supports_remote_control: bool,
+    pub supported_commands: Vec<String>,
+    pub playable_media_types: Vec<String>,
+    pub last_activity_date: Option<String>,
+    pub now_playing: Option<MediaItem>,
+    pub play_state: Option<RemotePlayState>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemotePlayState {
+    pub position_ticks: Option<i64>,
+    pub can_seek: bool,
+    pub is_paused: bool,
+    pub is_muted: bool,
+    pub volume_level: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaystateCommand {
+    Pause,
+    Unpause,
+    PlayPause,
+    Stop,
+    Seek,
+    Next,
+    Previous,
+}
+
+impl PlaystateCommand {
+    pub fn as_jellyfin(self) -> &'static str {
+        match self {
+            Self::Pause => "Pause",
+            Self::Unpause => "Unpause",
+            Self::PlayPause => "PlayPause",
+            Self::Stop => "Stop",
+            Self::Seek => "Seek",
+            Self::Next => "NextTrack",
+            Self::Previous => "PreviousTrack",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaystateRequest {
+    pub command: PlaystateCommand,
+    /// Target position for `seek`.
+    pub position_ticks: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VolumeRequest {
+    /// Volume from 0 to 100.
+    pub level: Option<u8>,
+    pub muted: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayMode {
+    #[default]
+    Now,
+    Next,
+    Last,
+}
+
+impl PlayMode {
+    pub fn as_jellyfin(self) -> &'static str {
+        match self {
+            Self::Now => "PlayNow",
+            Self::Next => "PlayNext",
+            Self::Last => "PlayLast",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlayItemsRequest {
+    pub item_ids: Vec<String>,
+    #[serde(default)]
+    pub mode: PlayMode,
+    pub start_position_ticks: Option<i64>,
+    /// Index into `item_ids` to start from.
+    pub start_index: Option<u32>,
+}
+
+impl From<JellyfinPlayState> for RemotePlayState {
+    fn from(state: JellyfinPlayState) -> Self {
+        Self {
+            position_ticks: state.position_ticks,
+            can_seek: state.can_seek,
+            is_paused: state.is_paused,
+            is_muted: state.is_muted,
+            volume_level: state.volume_level,
+        }
+    }
+}
+
+impl RemoteSession {
+    pub fn from_jellyfin(session: JellyfinSessionInfo, current_device_id: &str) -> Self {
+        Self {
+            is_current: session.device_id.as_deref() == Some(current_device_id),
+            id: session.id,
+            client: session.client,
+            device_name: session.device_name,
+            application_version: session.application_version,
+            user_name: session.user_name,
+            supports_remote_control: session.supports_remote_control,
+            supported_commands: session.supported_commands,
+            playable_media_types: session.playable_media_types,
+            last_activity_date: session.last_activity_date,
+            now_playing: session.now_playing_item.map(MediaItem::from),
+            play_state: session.play_state.map(RemotePlayState::from),
+        }
+    }
+}
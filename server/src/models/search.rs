@@ -0,0 +1,140 @@
+//
+//  media-savant-api
+//  models/search.rs
+//
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{image_url, ItemImages, JellyfinItem, JellyfinSearchHint};
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub library_id: Option<String>,
+    /// Comma separated subset of `movie,series,episode,person`.
+    pub types: Option<String>,
+    pub start_index: Option<u32>,
+    /// Page size for every group.
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SearchKind {
+    Movie,
+    Series,
+    Episode,
+    Person,
+}
+
+impl SearchKind {
+    pub const ALL: [SearchKind; 4] = [Self::Movie, Self::Series, Self::Episode, Self::Person];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "movie" | "movies" => Some(Self::Movie),
+            "series" | "show" | "shows" => Some(Self::Series),
+            "episode" | "episodes" => Some(Self::Episode),
+            "person" | "people" => Some(Self::Person),
+            _ => None,
+        }
+    }
+
+    pub fn from_jellyfin(item_type: &str) -> Option<Self> {
+        match item_type {
+            "Movie" => Some(Self::Movie),
+            "Series" => Some(Self::Series),
+            "Episode" => Some(Self::Episode),
+            "Person" => Some(Self::Person),
+            _ => None,
+        }
+    }
+
+    pub fn as_jellyfin(self) -> &'static str {
+        match self {
+            Self::Movie => "Movie",
+            Self::Series => "Series",
+            Self::Episode => "Episode",
+            Self::Person => "Person",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub id: String,
+    pub name: String,
+    pub item_type: String,
+    pub production_year: Option<i32>,
+    pub series_name: Option<String>,
+    pub index_number: Option<i32>,
+    pub parent_index_number: Option<i32>,
+    pub images: ItemImages,
+    pub score: u32,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchGroup {
+    pub items: Vec<SearchHit>,
+    pub total_record_count: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    pub query: String,
+    pub start_index: u32,
+    pub movies: SearchGroup,
+    pub series: SearchGroup,
+    pub episodes: SearchGroup,
+    pub people: SearchGroup,
+}
+
+impl From<JellyfinItem> for SearchHit {
+    fn from(item: JellyfinItem) -> Self {
+        Self {
+            images: ItemImages::from(&item),
+            id: item.id,
+            name: item.name,
+            item_type: item.item_type,
+            production_year: item.production_year,
+            series_name: item.series_name,
+            index_number: item.index_number,
+            parent_index_number: item.parent_index_number,
+            score: 0,
+        }
+    }
+}
+
+impl From<JellyfinSearchHint> for SearchHit {
+    fn from(hint: JellyfinSearchHint) -> Self {
+        let images = ItemImages {
+            primary: hint
+                .primary_image_tag
+                .as_deref()
+                .map(|tag| image_url(&hint.id, "Primary", tag)),
+            backdrop: hint.backdrop_image_tag.as_deref().map(|tag| {
+                let owner = hint.backdrop_image_item_id.as_deref().unwrap_or(&hint.id);
+                image_url(owner, "Backdrop", tag)
+            }),
+            thumb: hint.thumb_image_tag.as_deref().map(|tag| {
+                let owner = hint.thumb_image_item_id.as_deref().unwrap_or(&hint.id);
+                image_url(owner, "Thumb", tag)
+            }),
+            ..Default::default()
+        };
+
+        Self {
+            images,
+            id: hint.id,
+            name: hint.name,
+            item_type: hint.item_type,
+            production_year: hint.production_year,
+            series_name: hint.series,
+            index_number: hint.index_number,
+            parent_index_number: hint.parent_index_number,
+            score: 0,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//
+//  media-savant-api
+//  models/subtitles.rs
+//
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubtitleDelivery {
+    /// Served as WebVTT by `/api/subtitles`.
+    Vtt,
+    /// Image based; must be burned into the video by Jellyfin.
+    Encode,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleTrack {
+    pub index: i32,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub is_default: bool,
+    pub is_forced: bool,
+    pub is_external: bool,
+    pub delivery: SubtitleDelivery,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubtitleQuery {
+    pub media_source_id: Option<String>,
+    #[serde(default)]
+    pub offset_ms: i64,
+}
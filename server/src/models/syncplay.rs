@@ -0,0 +1,84 @@
+//
+//  media-savant-api
+//  models/syncplay.rs
+//
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::JellyfinSyncPlayGroup;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlayGroup {
+    pub id: String,
+    pub name: String,
+    /// Jellyfin group state: `Idle`, `Waiting`, `Paused` or `Playing`.
+    pub state: String,
+    pub participants: Vec<String>,
+    pub last_updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGroupRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeekRequest {
+    pub position_ticks: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupQueueMode {
+    /// Replace the group's queue and start playing it.
+    #[default]
+    Replace,
+    Next,
+    Last,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GroupQueueRequest {
+    pub item_ids: Vec<String>,
+    #[serde(default)]
+    pub mode: GroupQueueMode,
+    /// Only used with `replace`.
+    pub start_index: Option<u32>,
+    /// Only used with `replace`.
+    pub start_position_ticks: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistItemRequest {
+    /// Group playlist entry currently playing on this client.
+    pub playlist_item_id: String,
+}
+
+/// Playback state reported when this client is ready or buffering, so the group can wait.
+#[derive(Debug, Deserialize)]
+pub struct ReadyStateRequest {
+    /// Client time of the report as an ISO 8601 timestamp.
+    pub when: String,
+    pub position_ticks: i64,
+    pub is_playing: bool,
+    pub playlist_item_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncPlayPingRequest {
+    /// Measured round trip time to the server in milliseconds.
+    pub ping_ms: u64,
+}
+
+impl From<JellyfinSyncPlayGroup> for SyncPlayGroup {
+    fn from(group: JellyfinSyncPlayGroup) -> Self {
+        Self {
+            id: group.group_id,
+            name: group.group_name,
+            state: group.state,
+            participants: group.participants,
+            last_updated_at: group.last_updated_at,
+        }
+    }
+}
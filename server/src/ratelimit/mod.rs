@@ -0,0 +1,143 @@
+//
+//  media-savant-api
+//  ratelimit/mod.rs
+//
+
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use arc_swap::ArcSwap;
+use governor::clock::{Clock, DefaultClock};
+use governor::{DefaultKeyedRateLimiter, Quota};
+
+use crate::config::RateLimitConfig;
+use crate::state::AppState;
+
+/// How often state kept for clients that have gone quiet is dropped.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Per-client-IP request limit whose quota can be swapped while the server runs.
+pub struct RateLimiter {
+    current: ArcSwap<Limiter>,
+}
+
+struct Limiter {
+    config: RateLimitConfig,
+    limiter: DefaultKeyedRateLimiter<IpAddr>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(Limiter::new(config)),
+        }
+    }
+
+    /// Applies a new quota. Clients start over with a full burst, so this only happens when
+    /// the quota actually changed.
+    pub fn reconfigure(&self, config: &RateLimitConfig) {
+        if self.current.load().config != *config {
+            self.current.store(Arc::new(Limiter::new(config)));
+        }
+    }
+
+    /// Forgets clients whose quota has fully replenished, which the limiter would otherwise
+    /// remember for every address that ever made a request.
+    pub fn prune(&self) {
+        let current = self.current.load();
+        current.limiter.retain_recent();
+        current.limiter.shrink_to_fit();
+    }
+
+    /// `Err` carries how long the client has to wait before its next request is allowed.
+    fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.current
+            .load()
+            .limiter
+            .check_key(&ip)
+            .map_err(|not_until| not_until.wait_time_from(DefaultClock::default().now()))
+    }
+}
+
+/// Prunes the limiter every `PRUNE_INTERVAL` for as long as the server runs.
+pub fn prune_periodically(state: web::Data<AppState>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PRUNE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            state.rate_limiter.prune();
+        }
+    });
+}
+
+impl Limiter {
+    fn new(config: &RateLimitConfig) -> Self {
+        // Config validation guarantees both values are positive.
+        let period = Duration::from_nanos((1_000_000_000 / config.per_second.max(1)).max(1));
+        let burst = NonZeroU32::new(config.burst).unwrap_or(NonZeroU32::MIN);
+        let quota = Quota::with_period(period)
+            .expect("rate limit period is not zero")
+            .allow_burst(burst);
+
+        Self {
+            config: config.clone(),
+            limiter: DefaultKeyedRateLimiter::keyed(quota),
+        }
+    }
+}
+
+/// Rejects clients over their quota with `429` and the seconds to wait in `Retry-After`.
+pub async fn limit_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let verdict = match (req.app_data::<web::Data<AppState>>(), req.peer_addr()) {
+        (Some(state), Some(peer)) => state
+            .rate_limiter
+            .check(peer.ip())
+            .map_err(|wait| (state.metrics.clone(), wait)),
+        _ => Ok(()),
+    };
+
+    match verdict {
+        Ok(()) => Ok(next.call(req).await?.map_into_left_body()),
+        Err((metrics, wait)) => {
+            metrics.record_rate_limited();
+            let wait_secs = wait.as_secs_f64().ceil() as u64;
+            let response = HttpResponse::TooManyRequests()
+                .insert_header(("retry-after", wait_secs))
+                .insert_header(("x-ratelimit-after", wait_secs))
+                .finish();
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn prunes_clients_with_a_full_quota() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            per_second: 1000,
+            burst: 1,
+        });
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert!(limiter.check(ip).is_ok());
+        assert!(limiter.check(ip).is_err());
+        assert_eq!(limiter.current.load().limiter.len(), 1);
+
+        std::thread::sleep(Duration::from_millis(5));
+        limiter.prune();
+        assert!(limiter.current.load().limiter.is_empty());
+        assert!(limiter.check(ip).is_ok());
+    }
+}
@@ -8,6 +8,7 @@ use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
 use serde_json::json;
 use uuid::Uuid;
 
+use crate::jellyfin::Traffic;
 use crate::models::{
     ApiResponse, JellyfinAuthRequest, JellyfinAuthResponse, LoginRequest, SessionData, SessionInfo,
 };
@@ -33,22 +34,24 @@ async fn login(
         .clone()
         .unwrap_or_else(|| Uuid::new_v4().to_string());
 
+    let config = state.config();
     let auth_header = build_emby_auth_header(
-        &state.config.app.client_name,
-        &state.config.app.device_name,
+        &config.app.client_name,
+        &config.app.device_name,
         &device_id,
-        &state.config.app.client_version,
+        &config.app.client_version,
         None,
     );
 
     let jf_payload = JellyfinAuthRequest {
-        Username: payload.username.clone(),
-        Pw: payload.password.clone(),
+        username: payload.username.clone(),
+        pw: payload.password.clone(),
     };
 
     let url = format!("{server_url}/Users/AuthenticateByName");
     let response = state
-        .http
+        .transport
+        .client(&url, Traffic::Metadata)
         .post(url)
         .header("X-Emby-Authorization", auth_header)
         .json(&jf_payload)
@@ -83,9 +86,9 @@ async fn login(
     let session_id = Uuid::new_v4();
     let session = SessionData {
         session_id,
-        user_id: auth_response.User.Id.clone(),
-        username: auth_response.User.Name.clone(),
-        access_token: auth_response.AccessToken.clone(),
+        user_id: auth_response.user.id.clone(),
+        username: auth_response.user.name.clone(),
+        access_token: auth_response.access_token.clone(),
         server_url: server_url.clone(),
         device_id: device_id.clone(),
     };
@@ -96,11 +99,11 @@ async fn login(
         )));
     }
 
-    let cookie = Cookie::build(state.config.auth.cookie_name.clone(), session_id.to_string())
+    let cookie = Cookie::build(config.auth.cookie_name.clone(), session_id.to_string())
         .path("/")
         .http_only(true)
         .same_site(SameSite::Lax)
-        .secure(state.config.auth.cookie_secure)
+        .secure(config.auth.cookie_secure)
         .finish();
 
     let info = SessionInfo {
@@ -121,11 +124,12 @@ async fn logout(state: web::Data<AppState>, req: HttpRequest) -> impl Responder
         let _ = delete_session(&state, session_id).await;
     }
 
-    let cookie = Cookie::build(state.config.auth.cookie_name.clone(), "")
+    let config = state.config();
+    let cookie = Cookie::build(config.auth.cookie_name.clone(), "")
         .path("/")
         .http_only(true)
         .same_site(SameSite::Lax)
-        .secure(state.config.auth.cookie_secure)
+        .secure(config.auth.cookie_secure)
         .max_age(actix_web::cookie::time::Duration::seconds(0))
         .finish();
 
@@ -158,16 +162,38 @@ async fn me(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
 }
 
 pub fn session_id_from_request(state: &AppState, req: &HttpRequest) -> Option<Uuid> {
-    req.cookie(&state.config.auth.cookie_name)
+    req.cookie(&state.config().auth.cookie_name)
         .and_then(|cookie| Uuid::parse_str(cookie.value()).ok())
 }
 
+/// Resolves the caller's session, or the `401`/`500` response to return instead.
+pub async fn require_session(
+    state: &AppState,
+    req: &HttpRequest,
+) -> Result<SessionData, HttpResponse> {
+    let Some(session_id) = session_id_from_request(state, req) else {
+        return Err(HttpResponse::Unauthorized().json(ApiResponse::<()>::err("Missing session")));
+    };
+
+    match load_session(state, session_id).await {
+        Ok(Some(session)) => Ok(session),
+        Ok(None) => {
+            Err(HttpResponse::Unauthorized().json(ApiResponse::<()>::err("Session not found")))
+        }
+        Err(err) => Err(HttpResponse::InternalServerError().json(ApiResponse::<()>::err(
+            format!("Failed to load session: {err}"),
+        ))),
+    }
+}
+
+#[tracing::instrument(name = "session.load", skip_all)]
 pub async fn load_session(
     state: &AppState,
     session_id: Uuid,
 ) -> Result<Option<SessionData>, Box<dyn std::error::Error>> {
     let mut conn = state.redis.lock().await;
     let key = format!("session:{session_id}");
+    let _timer = state.metrics.redis_timer("GET");
     let data: Option<String> = redis::cmd("GET").arg(&key).query_async(&mut *conn).await?;
 
     if let Some(value) = data {
@@ -186,6 +212,7 @@ async fn save_session(
     let key = format!("session:{}", session.session_id);
     let value = serde_json::to_string(session)?;
 
+    let _timer = state.metrics.redis_timer("SET");
     redis::cmd("SET")
         .arg(&key)
         .arg(value)
@@ -200,6 +227,7 @@ async fn delete_session(
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut conn = state.redis.lock().await;
     let key = format!("session:{session_id}");
+    let _timer = state.metrics.redis_timer("DEL");
     redis::cmd("DEL")
         .arg(&key)
         .query_async::<_, ()>(&mut *conn)
@@ -228,11 +256,12 @@ fn build_emby_auth_header(
 }
 
 pub fn build_token_header(state: &AppState, session: &SessionData) -> String {
+    let config = state.config();
     build_emby_auth_header(
-        &state.config.app.client_name,
-        &state.config.app.device_name,
+        &config.app.client_name,
+        &config.app.device_name,
         &session.device_id,
-        &state.config.app.client_version,
-        Some(&session.access_token),
+        &config.app.client_version,
+        Some(session.access_token.expose()),
     )
 }
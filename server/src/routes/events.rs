@@ -0,0 +1,91 @@
+//
+//  media-savant-api
+//  routes/events.rs
+//
+
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use bytes::Bytes;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::interval;
+
+use crate::models::EventsQuery;
+use crate::routes::auth::{load_session, require_session};
+use crate::state::AppState;
+
+/// Comment frames sent while idle so proxies keep the stream open. The session is re-checked
+/// at the same pace, so a browser that signs out stops receiving events.
+const HEARTBEAT: Duration = Duration::from_secs(15);
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(events);
+}
+
+/// Streams Jellyfin server events for the signed-in profile as server-sent events.
+#[get("/events")]
+async fn events(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<EventsQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let types: Option<Vec<String>> = query.types.as_deref().map(|types| {
+        types
+            .split(',')
+            .map(|kind| kind.trim().to_string())
+            .filter(|kind| !kind.is_empty())
+            .collect()
+    });
+    let receiver = state.events.subscribe(&state, &session);
+    let session_id = session.session_id;
+
+    let stream = futures_util::stream::unfold(
+        (receiver, interval(HEARTBEAT)),
+        move |(mut receiver, mut heartbeat)| {
+            let types = types.clone();
+            let state = state.clone();
+            async move {
+                loop {
+                    let frame = tokio::select! {
+                        event = receiver.recv() => match event {
+                            Ok(event) => {
+                                // Connection status is always delivered so clients can resync.
+                                let wanted = event.kind == "Connection"
+                                    || types.as_ref().is_none_or(|types| types.contains(&event.kind));
+                                if !wanted {
+                                    continue;
+                                }
+                                event.to_sse()
+                            }
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => return None,
+                        },
+                        _ = heartbeat.tick() => {
+                            // Redis errors keep the stream open; only a deleted session ends it.
+                            if matches!(load_session(&state, session_id).await, Ok(None)) {
+                                return None;
+                            }
+                            ": heartbeat\n\n".to_string()
+                        }
+                    };
+                    return Some((
+                        Ok::<_, actix_web::Error>(Bytes::from(frame)),
+                        (receiver, heartbeat),
+                    ));
+                }
+            }
+        },
+    );
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream)
+}
@@ -3,15 +3,129 @@
 //  routes/health.rs
 //
 
+use std::time::Instant;
+
 use actix_web::{get, web, HttpResponse, Responder};
+use futures_util::future::join_all;
+use tokio::time::timeout;
 
-use crate::models::ApiResponse;
+use crate::jellyfin::Traffic;
+use crate::models::{
+    ApiResponse, ComponentHealth, ConfigHealth, HealthStatus, JellyfinServerHealth, Liveness,
+    Readiness,
+};
+use crate::state::AppState;
 
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(health_check);
+    cfg.service(health_check)
+        .service(liveness)
+        .service(readiness)
+        .service(metadata_cache_stats);
 }
 
 #[get("/health")]
 async fn health_check() -> impl Responder {
     HttpResponse::Ok().json(ApiResponse::ok("ok"))
 }
+
+/// Answers as long as the process can serve HTTP; dependencies are not consulted.
+#[get("/health/live")]
+async fn liveness(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::ok(Liveness {
+        status: HealthStatus::Up,
+        uptime_secs: state.started_at.elapsed().as_secs(),
+    }))
+}
+
+/// Probes every dependency; `503` when the API cannot serve requests.
+#[get("/health/ready")]
+async fn readiness(state: web::Data<AppState>) -> impl Responder {
+    let servers = state.config().jellyfin.servers.clone();
+    let (session_store, jellyfin) = futures_util::join!(
+        check_session_store(&state),
+        join_all(
+            servers
+                .iter()
+                .map(|server| check_jellyfin(&state, &server.url))
+        ),
+    );
+    let reload_pending = state.reload.pending();
+    let reload_error = state.reload.last_error();
+    let config = ConfigHealth {
+        status: if reload_pending || reload_error.is_some() {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Up
+        },
+        reload_pending,
+        error: reload_error,
+        restart_required: state.reload.restart_required(),
+    };
+
+    let draining = state.shutdown.is_draining();
+    let status = Readiness::overall(draining, &session_store, &jellyfin, &config);
+    let readiness = Readiness {
+        status,
+        draining,
+        session_store,
+        jellyfin,
+        config,
+    };
+    if status == HealthStatus::Down {
+        HttpResponse::ServiceUnavailable().json(ApiResponse {
+            success: false,
+            ..ApiResponse::ok(readiness)
+        })
+    } else {
+        HttpResponse::Ok().json(ApiResponse::ok(readiness))
+    }
+}
+
+/// Hit and miss counts for the Jellyfin metadata cache.
+#[get("/health/cache")]
+async fn metadata_cache_stats(state: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::ok(state.metadata.stats()))
+}
+
+async fn check_session_store(state: &AppState) -> ComponentHealth {
+    let started = Instant::now();
+    let ping = async {
+        let mut conn = state.redis.lock().await;
+        let _timer = state.metrics.redis_timer("PING");
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut *conn)
+            .await
+    };
+
+    match timeout(state.config().health.check_timeout, ping).await {
+        Ok(Ok(_)) => ComponentHealth::up(started.elapsed().as_millis() as u64),
+        Ok(Err(err)) => ComponentHealth::down(format!("PING failed: {err}")),
+        Err(_) => ComponentHealth::down("PING timed out"),
+    }
+}
+
+async fn check_jellyfin(state: &AppState, url: &str) -> JellyfinServerHealth {
+    let started = Instant::now();
+    let ping = state
+        .transport
+        .client(url, Traffic::Metadata)
+        .get(format!("{url}/System/Ping"))
+        .timeout(state.config().health.check_timeout)
+        .send()
+        .await;
+
+    let health = match ping {
+        Ok(response) if response.status().is_success() => {
+            ComponentHealth::up(started.elapsed().as_millis() as u64)
+        }
+        Ok(response) => ComponentHealth::down(format!("Ping returned {}", response.status())),
+        Err(err) if err.is_timeout() => ComponentHealth::down("Ping timed out"),
+        Err(err) if err.is_connect() => ComponentHealth::down("Connection refused or unreachable"),
+        Err(_) => ComponentHealth::down("Ping failed"),
+    };
+    JellyfinServerHealth {
+        url: url.to_string(),
+        health,
+        circuit: state.breakers.state(url),
+    }
+}
@@ -0,0 +1,181 @@
+//
+//  media-savant-api
+//  routes/home.rs
+//
+
+use std::collections::HashSet;
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use futures_util::future::join_all;
+use tracing::warn;
+
+use crate::jellyfin::{JellyfinClient, JellyfinError};
+use crate::models::{
+    ApiResponse, HomeFeed, HomeQuery, JellyfinItem, JellyfinItemsResponse, LatestSection,
+    LibraryView, MediaItem,
+};
+use crate::routes::auth::require_session;
+use crate::routes::library::LIST_FIELDS;
+use crate::state::AppState;
+
+const DEFAULT_ROW_SIZE: u32 = 12;
+const MAX_ROW_SIZE: u32 = 50;
+
+/// Library collection types that never feed "recently added" rows.
+const SKIPPED_COLLECTIONS: &[&str] =
+    &["playlists", "boxsets", "livetv", "music", "books", "photos"];
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(home_feed);
+}
+
+#[get("/home")]
+async fn home_feed(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<HomeQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ROW_SIZE)
+        .clamp(1, MAX_ROW_SIZE)
+        .to_string();
+
+    let resume_path = format!("/Users/{}/Items/Resume", client.user_id());
+    let resume_params = [
+        ("Limit", limit.clone()),
+        ("MediaTypes", "Video".to_string()),
+        ("Fields", LIST_FIELDS.to_string()),
+    ];
+    let next_up_params = [
+        ("UserId", client.user_id().to_string()),
+        ("Limit", limit.clone()),
+        ("Fields", LIST_FIELDS.to_string()),
+    ];
+
+    let resume = client.get_json::<JellyfinItemsResponse>(&resume_path, &resume_params);
+    let next_up = client.get_json::<JellyfinItemsResponse>("/Shows/NextUp", &next_up_params);
+    let latest = latest_sections(&client, &limit);
+
+    let (resume, next_up, latest) = futures_util::join!(resume, next_up, latest);
+    let (resume, next_up, latest) = match (resume, next_up, latest) {
+        (Ok(resume), Ok(next_up), Ok(latest)) => (resume, next_up, latest),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => return err.error_response(),
+    };
+
+    HttpResponse::Ok().json(ApiResponse::ok(merge_feed(
+        resume.items,
+        next_up.items,
+        latest,
+    )))
+}
+
+/// Fetches the user's views, then every video library's latest items concurrently.
+async fn latest_sections(
+    client: &JellyfinClient<'_>,
+    limit: &str,
+) -> Result<Vec<(LibraryView, Vec<JellyfinItem>)>, JellyfinError> {
+    let views_path = format!("/Users/{}/Views", client.user_id());
+    let views = client
+        .get_json::<JellyfinItemsResponse>(&views_path, &[])
+        .await?
+        .items
+        .into_iter()
+        .filter(|view| {
+            !view
+                .collection_type
+                .as_deref()
+                .is_some_and(|kind| SKIPPED_COLLECTIONS.contains(&kind))
+        })
+        .map(LibraryView::from)
+        .collect::<Vec<_>>();
+
+    let latest_path = format!("/Users/{}/Items/Latest", client.user_id());
+    let params: Vec<[(&str, String); 3]> = views
+        .iter()
+        .map(|view| {
+            [
+                ("ParentId", view.id.clone()),
+                ("Limit", limit.to_string()),
+                ("Fields", LIST_FIELDS.to_string()),
+            ]
+        })
+        .collect();
+    let requests = params
+        .iter()
+        .map(|params| client.get_json::<Vec<JellyfinItem>>(&latest_path, params));
+    let results = join_all(requests).await;
+
+    // One broken library should not blank the whole home screen.
+    Ok(views
+        .into_iter()
+        .zip(results)
+        .filter_map(|(view, result)| match result {
+            Ok(items) => Some((view, items)),
+            Err(err) => {
+                warn!("Skipping latest items for library {}: {err}", view.id);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Combines the rows, dropping anything already surfaced by an earlier row.
+///
+/// Resume wins over next up for the same series, since the in-progress episode is the one the
+/// user actually wants to continue.
+fn merge_feed(
+    resume: Vec<JellyfinItem>,
+    next_up: Vec<JellyfinItem>,
+    latest: Vec<(LibraryView, Vec<JellyfinItem>)>,
+) -> HomeFeed {
+    let mut seen_items: HashSet<String> = HashSet::new();
+    let mut seen_series: HashSet<String> = HashSet::new();
+
+    let continue_watching: Vec<MediaItem> = resume
+        .into_iter()
+        .filter(|item| seen_items.insert(item.id.clone()))
+        .inspect(|item| {
+            if let Some(series_id) = &item.series_id {
+                seen_series.insert(series_id.clone());
+            }
+        })
+        .map(MediaItem::from)
+        .collect();
+
+    let next_up: Vec<MediaItem> = next_up
+        .into_iter()
+        .filter(|item| {
+            !item
+                .series_id
+                .as_ref()
+                .is_some_and(|series_id| seen_series.contains(series_id))
+        })
+        .filter(|item| seen_items.insert(item.id.clone()))
+        .map(MediaItem::from)
+        .collect();
+
+    let recently_added = latest
+        .into_iter()
+        .map(|(library, items)| LatestSection {
+            library,
+            items: items
+                .into_iter()
+                .filter(|item| !seen_items.contains(&item.id))
+                .map(MediaItem::from)
+                .collect(),
+        })
+        .filter(|section| !section.items.is_empty())
+        .collect();
+
+    HomeFeed {
+        continue_watching,
+        next_up,
+        recently_added,
+    }
+}
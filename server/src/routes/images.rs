@@ -0,0 +1,321 @@
+//
+//  media-savant-api
+//  routes/images.rs
+//
+
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bytes::Bytes;
+use tracing::warn;
+
+use crate::images::{
+    average_color, cache_key, encode_blur_hash, is_transcodable, is_valid_blur_hash, negotiate,
+    raster_content_type, render_placeholder, transcode, IMAGE_TYPES,
+};
+use crate::jellyfin::JellyfinClient;
+use crate::models::{
+    ApiResponse, ImageQuery, PlaceholderData, PlaceholderFormat, PlaceholderQuery, SessionData,
+};
+use crate::routes::auth::require_session;
+use crate::state::AppState;
+
+/// Untagged artwork can change under the same URL, so it is only reused for an hour.
+const UNTAGGED_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+/// Default and maximum edge length of rendered placeholders; they are upscaled and blurred.
+const PLACEHOLDER_SIZE: u32 = 32;
+const MAX_PLACEHOLDER_SIZE: u32 = 128;
+/// How long a failed transcode is remembered before the next request tries again.
+const TRANSCODE_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+/// Width of the rendition hashed when Jellyfin has no BlurHash for the artwork.
+const PLACEHOLDER_SOURCE_WIDTH: u32 = 128;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/images").service(placeholder).service(artwork));
+}
+
+#[get("/{item_id}/{image_type}")]
+async fn artwork(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<ImageQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let (item_id, image_type) = path.into_inner();
+    if !IMAGE_TYPES.contains(&image_type.as_str()) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("Unknown image type"));
+    }
+
+    let accept = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|val| val.to_str().ok());
+    let format = negotiate(accept, &state.config().images);
+
+    let base_key = cache_key(&session.server_url, &item_id, &image_type, &query);
+    let key = match format {
+        Some(format) => format!("{base_key}_{}", format.key_suffix()),
+        None => base_key.clone(),
+    };
+    let (cache_control, max_age) = freshness(&query);
+
+    // Untagged artwork can change under the same key, so only tagged renditions get an ETag.
+    // Whatever is served for `key` (the variant, or the original when its format is never
+    // transcoded) carries that key's ETag. An original served because transcoding failed gets
+    // its own, so a later successful transcode isn't mistaken for the same bytes.
+    let tagged = query.tag.is_some();
+    let etag = tagged.then(|| format!("\"{key}\""));
+    let original_etag = tagged.then(|| format!("\"{base_key}\""));
+    if let Some(etag) = etag.as_ref().filter(|etag| etag_matches(&req, etag)) {
+        return not_modified(etag, cache_control);
+    }
+
+    if let Some(cached) = state.images.get(&key, max_age).await {
+        return artwork_response(cached.bytes, cached.content_type, etag, cache_control);
+    }
+
+    let failed_key = format!("{key}_failed");
+    let recently_failed = format.is_some()
+        && state
+            .images
+            .get(&failed_key, Some(TRANSCODE_RETRY_AFTER))
+            .await
+            .is_some();
+    let fallback_match = original_etag
+        .as_ref()
+        .filter(|etag| recently_failed && etag_matches(&req, etag));
+    if let Some(etag) = fallback_match {
+        return not_modified(etag, cache_control);
+    }
+
+    let (bytes, content_type) =
+        match load_original(&state, &session, &item_id, &image_type, &query, max_age).await {
+            Ok(original) => original,
+            Err(response) => return response,
+        };
+
+    let Some(format) = format.filter(|_| is_transcodable(&content_type)) else {
+        return artwork_response(bytes, content_type, etag, cache_control);
+    };
+    if recently_failed {
+        return artwork_response(bytes, content_type, original_etag, cache_control);
+    }
+
+    let permit = state.images.transcode_permit().await;
+    // Another request may have finished the same transcode while this one waited.
+    if let Some(cached) = state.images.get(&key, max_age).await {
+        return artwork_response(cached.bytes, cached.content_type, etag, cache_control);
+    }
+
+    let config = state.config().images.clone();
+    let source = bytes.clone();
+    let transcoded = web::block(move || {
+        let _permit = permit;
+        transcode(&source, format, &config)
+    })
+    .await;
+    match transcoded {
+        Ok(Ok(encoded)) => {
+            let encoded = Bytes::from(encoded);
+            state
+                .images
+                .put(&key, format.content_type(), &encoded)
+                .await;
+            artwork_response(
+                encoded,
+                format.content_type().to_string(),
+                etag,
+                cache_control,
+            )
+        }
+        Ok(Err(err)) => {
+            warn!("Serving original artwork for {base_key}, transcoding failed: {err}");
+            // Remembered for a while so the transcode isn't retried on every request.
+            state
+                .images
+                .put(&failed_key, &content_type, &Bytes::new())
+                .await;
+            artwork_response(bytes, content_type, original_etag, cache_control)
+        }
+        Err(err) => {
+            warn!("Serving original artwork for {base_key}, transcoding failed: {err}");
+            artwork_response(bytes, content_type, original_etag, cache_control)
+        }
+    }
+}
+
+#[get("/{item_id}/{image_type}/placeholder")]
+async fn placeholder(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<PlaceholderQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let (item_id, image_type) = path.into_inner();
+    if !IMAGE_TYPES.contains(&image_type.as_str()) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("Unknown image type"));
+    }
+
+    let width = query
+        .width
+        .unwrap_or(PLACEHOLDER_SIZE)
+        .clamp(1, MAX_PLACEHOLDER_SIZE);
+    let height = query
+        .height
+        .unwrap_or(PLACEHOLDER_SIZE)
+        .clamp(1, MAX_PLACEHOLDER_SIZE);
+    let source = ImageQuery {
+        tag: query.tag.clone(),
+        index: query.index,
+        max_width: Some(PLACEHOLDER_SOURCE_WIDTH),
+        ..Default::default()
+    };
+    let (cache_control, max_age) = freshness(&source);
+
+    let blur_hash = match &query.hash {
+        Some(hash) if is_valid_blur_hash(hash) => hash.clone(),
+        Some(_) => {
+            return HttpResponse::BadRequest().json(ApiResponse::<()>::err("Invalid blurhash"));
+        }
+        // Jellyfin had no hash for this artwork, so derive one from a small rendition.
+        None => {
+            let original =
+                load_original(&state, &session, &item_id, &image_type, &source, max_age).await;
+            let (bytes, _) = match original {
+                Ok(original) => original,
+                Err(response) => return response,
+            };
+            match web::block(move || encode_blur_hash(&bytes)).await {
+                Ok(Ok(hash)) => hash,
+                Ok(Err(err)) => {
+                    return HttpResponse::BadGateway().json(ApiResponse::<()>::err(format!(
+                        "Failed to compute blurhash: {err}"
+                    )));
+                }
+                Err(err) => {
+                    return HttpResponse::InternalServerError().json(ApiResponse::<()>::err(
+                        format!("Failed to compute blurhash: {err}"),
+                    ));
+                }
+            }
+        }
+    };
+
+    let png = match render_placeholder(&blur_hash, width, height) {
+        Ok(png) => png,
+        Err(err) => {
+            return HttpResponse::InternalServerError().json(ApiResponse::<()>::err(format!(
+                "Failed to render placeholder: {err}"
+            )));
+        }
+    };
+
+    match query.format {
+        PlaceholderFormat::Png => HttpResponse::Ok()
+            .content_type("image/png")
+            .insert_header((header::CACHE_CONTROL, cache_control))
+            .body(png),
+        PlaceholderFormat::DataUri => HttpResponse::Ok()
+            .insert_header((header::CACHE_CONTROL, cache_control))
+            .json(ApiResponse::ok(PlaceholderData {
+                average_color: average_color(&blur_hash),
+                data_uri: format!("data:image/png;base64,{}", BASE64.encode(png)),
+                blur_hash,
+            })),
+    }
+}
+
+/// Returns the untransformed rendition from the cache, fetching it from Jellyfin on a miss.
+async fn load_original(
+    state: &AppState,
+    session: &SessionData,
+    item_id: &str,
+    image_type: &str,
+    query: &ImageQuery,
+    max_age: Option<Duration>,
+) -> Result<(Bytes, String), HttpResponse> {
+    let key = cache_key(&session.server_url, item_id, image_type, query);
+    if let Some(cached) = state.images.get(&key, max_age).await {
+        return Ok((cached.bytes, cached.content_type));
+    }
+
+    let client = JellyfinClient::new(state, session);
+
+    let mut upstream_path = format!("/Items/{item_id}/Images/{image_type}");
+    if let Some(index) = query.index {
+        upstream_path.push_str(&format!("/{index}"));
+    }
+    let (bytes, content_type) = client
+        .get_bytes(&upstream_path, &query.upstream_params())
+        .await
+        .map_err(|err| err.error_response())?;
+    let Some(content_type) = raster_content_type(&bytes) else {
+        warn!("Refusing artwork for {item_id} that is not a raster image ({content_type:?})");
+        return Err(HttpResponse::BadGateway().json(ApiResponse::<()>::err(
+            "Jellyfin returned an unsupported image type",
+        )));
+    };
+    let content_type = content_type.to_string();
+
+    state.images.put(&key, &content_type, &bytes).await;
+    Ok((bytes, content_type))
+}
+
+/// Cache-Control header and local freshness window for a rendition.
+fn freshness(query: &ImageQuery) -> (&'static str, Option<Duration>) {
+    if query.tag.is_some() {
+        ("private, max-age=31536000, immutable", None)
+    } else {
+        ("private, max-age=3600", Some(UNTAGGED_MAX_AGE))
+    }
+}
+
+/// Every rendition varies on `Accept`, since the same URL may yield AVIF, WebP or the original.
+fn artwork_response(
+    bytes: Bytes,
+    content_type: String,
+    etag: Option<String>,
+    cache_control: &str,
+) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(content_type)
+        .insert_header((header::CACHE_CONTROL, cache_control.to_string()))
+        .insert_header((header::VARY, "Accept"));
+    if let Some(etag) = etag {
+        response.insert_header((header::ETAG, etag));
+    }
+    response.body(bytes)
+}
+
+fn not_modified(etag: &str, cache_control: &str) -> HttpResponse {
+    HttpResponse::NotModified()
+        .insert_header((header::ETAG, etag.to_string()))
+        .insert_header((header::CACHE_CONTROL, cache_control.to_string()))
+        .insert_header((header::VARY, "Accept"))
+        .finish()
+}
+
+fn etag_matches(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|val| val.to_str().ok())
+        .is_some_and(|val| {
+            val.split(',')
+                .map(|candidate| candidate.trim().trim_start_matches("W/"))
+                .any(|candidate| candidate == etag || candidate == "*")
+        })
+}
@@ -0,0 +1,244 @@
+//
+//  media-savant-api
+//  routes/library.rs
+//
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, ResponseError};
+
+use crate::jellyfin::JellyfinClient;
+use crate::models::{
+    ApiResponse, EpisodesQuery, ItemPage, JellyfinItem, JellyfinItemsResponse, LibraryItemsQuery,
+    LibraryView, MediaItem, MediaItemDetail, SimilarItemsQuery,
+};
+use crate::routes::auth::require_session;
+use crate::state::AppState;
+
+/// Fields requested for every list of items.
+pub const LIST_FIELDS: &str = "Overview,Genres,DateCreated,PremiereDate,ChildCount,ParentId";
+/// Fields requested for a single item's detail page.
+const DETAIL_FIELDS: &str =
+    "Overview,Genres,DateCreated,PremiereDate,ChildCount,ParentId,People,Studios,Tags,Taglines";
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/library")
+            .service(list_views)
+            .service(list_items)
+            .service(item_details)
+            .service(similar_items)
+            .service(list_seasons)
+            .service(list_episodes),
+    );
+}
+
+#[get("/views")]
+async fn list_views(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let path = format!("/Users/{}/Views", client.user_id());
+    match client.get_json::<JellyfinItemsResponse>(&path, &[]).await {
+        Ok(response) => {
+            let views: Vec<LibraryView> =
+                response.items.into_iter().map(LibraryView::from).collect();
+            HttpResponse::Ok().json(ApiResponse::ok(views))
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[get("/views/{library_id}/items")]
+async fn list_items(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<LibraryItemsQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let query = query.into_inner();
+    let mut params = vec![
+        ("ParentId", path.into_inner()),
+        ("Recursive", "true".to_string()),
+        ("Fields", LIST_FIELDS.to_string()),
+        (
+            "IncludeItemTypes",
+            query
+                .item_types
+                .unwrap_or_else(|| "Movie,Series".to_string()),
+        ),
+        (
+            "SortBy",
+            query
+                .sort_by
+                .map(|sort| sort.as_jellyfin())
+                .unwrap_or("SortName")
+                .to_string(),
+        ),
+        (
+            "SortOrder",
+            query
+                .sort_order
+                .map(|order| order.as_jellyfin())
+                .unwrap_or("Ascending")
+                .to_string(),
+        ),
+        ("StartIndex", query.start_index.unwrap_or(0).to_string()),
+        ("Limit", page_size(query.limit).to_string()),
+    ];
+    if let Some(genres) = query.genres.filter(|genres| !genres.is_empty()) {
+        // Jellyfin separates genre filters with `|`.
+        params.push((
+            "Genres",
+            genres
+                .split(',')
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join("|"),
+        ));
+    }
+    if let Some(years) = query.years.filter(|years| !years.is_empty()) {
+        params.push(("Years", years));
+    }
+    if let Some(played) = query.played {
+        let filter = if played { "IsPlayed" } else { "IsUnplayed" };
+        params.push(("Filters", filter.to_string()));
+    }
+
+    let path = format!("/Users/{}/Items", client.user_id());
+    match client
+        .get_json::<JellyfinItemsResponse>(&path, &params)
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(ApiResponse::ok(ItemPage::from(response))),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[get("/items/{item_id}")]
+async fn item_details(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let path = format!("/Users/{}/Items/{}", client.user_id(), path.into_inner());
+    let params = [("Fields", DETAIL_FIELDS.to_string())];
+    match client.get_json::<JellyfinItem>(&path, &params).await {
+        Ok(item) => HttpResponse::Ok().json(ApiResponse::ok(MediaItemDetail::from(item))),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[get("/shows/{series_id}/seasons")]
+async fn list_seasons(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let path = format!("/Shows/{}/Seasons", path.into_inner());
+    let params = [
+        ("UserId", client.user_id().to_string()),
+        ("Fields", LIST_FIELDS.to_string()),
+    ];
+    match client
+        .get_json::<JellyfinItemsResponse>(&path, &params)
+        .await
+    {
+        Ok(response) => {
+            let seasons: Vec<MediaItem> = response.items.into_iter().map(MediaItem::from).collect();
+            HttpResponse::Ok().json(ApiResponse::ok(seasons))
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[get("/shows/{series_id}/episodes")]
+async fn list_episodes(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<EpisodesQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let query = query.into_inner();
+    let path = format!("/Shows/{}/Episodes", path.into_inner());
+    let mut params = vec![
+        ("UserId", client.user_id().to_string()),
+        ("Fields", LIST_FIELDS.to_string()),
+        ("StartIndex", query.start_index.unwrap_or(0).to_string()),
+        ("Limit", page_size(query.limit).to_string()),
+    ];
+    if let Some(season_id) = query.season_id {
+        params.push(("SeasonId", season_id));
+    }
+
+    match client
+        .get_json::<JellyfinItemsResponse>(&path, &params)
+        .await
+    {
+        Ok(response) => HttpResponse::Ok().json(ApiResponse::ok(ItemPage::from(response))),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[get("/items/{item_id}/similar")]
+async fn similar_items(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<SimilarItemsQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let path = format!("/Items/{}/Similar", path.into_inner());
+    let params = [
+        ("UserId", client.user_id().to_string()),
+        ("Fields", LIST_FIELDS.to_string()),
+        ("Limit", query.limit.unwrap_or(12).clamp(1, MAX_PAGE_SIZE).to_string()),
+    ];
+    match client
+        .get_json::<JellyfinItemsResponse>(&path, &params)
+        .await
+    {
+        Ok(response) => {
+            let items: Vec<MediaItem> = response.items.into_iter().map(MediaItem::from).collect();
+            HttpResponse::Ok().json(ApiResponse::ok(items))
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+fn page_size(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
@@ -0,0 +1,89 @@
+//
+//  media-savant-api
+//  routes/metrics.rs
+//
+
+use actix_web::http::header;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use ring::digest::{digest, SHA256};
+use tracing::warn;
+
+use crate::state::AppState;
+
+/// Keys fetched per SCAN round trip while counting sessions.
+const SCAN_BATCH: usize = 500;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(metrics);
+}
+
+/// Prometheus scrape endpoint, served outside `/api` where scrapers expect it. Open unless
+/// `metrics.token` is set, so without one it belongs behind the reverse proxy.
+#[get("/metrics")]
+async fn metrics(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let config = state.config();
+    let token = config.metrics.token.as_ref();
+    if token.is_some_and(|token| !bearer_matches(&req, token.expose())) {
+        return HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .finish();
+    }
+
+    if state
+        .metrics
+        .claim_session_count(config.metrics.sessions_refresh)
+    {
+        match count_sessions(&state).await {
+            Ok(count) => state.metrics.set_sessions(count),
+            Err(err) => warn!("Failed to count sessions: {err}"),
+        }
+    }
+
+    match state.metrics.render() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4")
+            .body(body),
+        Err(err) => {
+            HttpResponse::InternalServerError().body(format!("Failed to render metrics: {err}"))
+        }
+    }
+}
+
+/// Compares digests rather than the tokens themselves, so the time taken says nothing about
+/// how much of the token matched.
+fn bearer_matches(req: &HttpRequest, token: &str) -> bool {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.strip_prefix("Bearer "))
+        .is_some_and(|sent| {
+            digest(&SHA256, sent.trim().as_bytes()).as_ref()
+                == digest(&SHA256, token.as_bytes()).as_ref()
+        })
+}
+
+/// Counts sessions a batch at a time, releasing the shared connection between batches so
+/// requests aren't held up for the whole scan.
+async fn count_sessions(state: &AppState) -> Result<i64, redis::RedisError> {
+    let _timer = state.metrics.redis_timer("SCAN");
+
+    let mut cursor: u64 = 0;
+    let mut count = 0;
+    loop {
+        let mut conn = state.redis.lock().await;
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg("session:*")
+            .arg("COUNT")
+            .arg(SCAN_BATCH)
+            .query_async(&mut *conn)
+            .await?;
+        drop(conn);
+        count += keys.len() as i64;
+        if next == 0 {
+            return Ok(count);
+        }
+        cursor = next;
+    }
+}
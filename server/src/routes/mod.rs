@@ -5,19 +5,40 @@
 
 use actix_web::web::{scope, ServiceConfig};
 
-mod auth;
+pub mod auth;
+mod events;
 mod health;
+mod home;
+mod images;
+mod library;
+mod metrics;
 mod proxy;
+mod proxy_socket;
+mod queue;
+mod remote;
+mod search;
 mod setup;
 mod stream;
+mod subtitles;
+mod syncplay;
 
 pub fn init(cfg: &mut ServiceConfig) {
+    cfg.configure(metrics::init);
     cfg.service(
         scope("/api")
+            .configure(events::init)
             .configure(health::init)
+            .configure(home::init)
+            .configure(images::init)
+            .configure(library::init)
             .configure(auth::init)
             .configure(proxy::init)
+            .configure(queue::init)
+            .configure(remote::init)
+            .configure(search::init)
             .configure(setup::init)
-            .configure(stream::init),
+            .configure(stream::init)
+            .configure(subtitles::init)
+            .configure(syncplay::init),
     );
 }
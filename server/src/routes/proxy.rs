@@ -3,17 +3,27 @@
 //  routes/proxy.rs
 //
 
-use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{
+    guard, http::StatusCode, web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
 use bytes::Bytes;
-use futures_util::TryFutureExt;
+use futures_util::StreamExt;
 
-use crate::models::ApiResponse;
+use crate::jellyfin::{execute, JellyfinError, MetadataCache, Traffic};
+use crate::models::{ApiResponse, JellyfinPlaybackProgressInfo};
 use crate::routes::auth::{build_token_header, load_session, session_id_from_request};
+use crate::routes::proxy_socket;
 use crate::state::AppState;
 
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/jellyfin")
+            .route(
+                "/{tail:.*}",
+                web::get()
+                    .guard(guard::fn_guard(proxy_socket::is_websocket_upgrade))
+                    .to(proxy_socket::passthrough),
+            )
             .route("/{tail:.*}", web::to(proxy_request))
             .route("", web::to(proxy_request)),
     );
@@ -55,10 +65,51 @@ async fn proxy_request(
                 .json(ApiResponse::<()>::err("Unsupported HTTP method"))
         }
     };
+
+    // Ranged reads are never cached; only whole metadata documents are.
+    let ranged = req.headers().contains_key("range");
+    let traffic = if ranged {
+        Traffic::Streaming
+    } else {
+        Traffic::for_path(tail)
+    };
+    let cache_ttl = if ranged {
+        None
+    } else {
+        state.metadata.ttl_for(&method, tail)
+    };
+    let cache_key = cache_ttl.map(|_| MetadataCache::key(&session, tail, query));
+    if let Some(hit) = cache_key.as_deref().and_then(|key| state.metadata.get(key)) {
+        let mut builder = HttpResponse::Ok();
+        if let Some(content_type) = hit.content_type {
+            builder.insert_header(("content-type", content_type));
+        }
+        return builder.insert_header(("x-cache", "HIT")).body(hit.body);
+    }
+    let invalidates_cache = MetadataCache::invalidated_by(&method, tail);
+    let progress = is_progress_report(&method, tail)
+        .then(|| serde_json::from_slice::<JellyfinPlaybackProgressInfo>(&body).ok())
+        .flatten();
+
+    // Streams register like `/stream` does, so shutdown waits for them and refuses new ones.
+    let ticket = if traffic == Traffic::Streaming {
+        match state.shutdown.stream_started(&session, streamed_item(tail)) {
+            Some(ticket) => Some(ticket),
+            None => {
+                return HttpResponse::ServiceUnavailable()
+                    .insert_header(("retry-after", "10"))
+                    .json(ApiResponse::<()>::err("Server is shutting down"))
+            }
+        }
+    } else {
+        None
+    };
+
     let auth_header = build_token_header(&state, &session);
 
     let mut request = state
-        .http
+        .transport
+        .client(&target, traffic)
         .request(method, target)
         .header("X-Emby-Authorization", auth_header);
 
@@ -73,14 +124,18 @@ async fn proxy_request(
         request = request.header("accept", accept);
     }
 
-    let response = request.body(body).send().map_err(|err| err.to_string()).await;
+    let response = match request.body(body).build() {
+        Ok(request) => execute(&state, traffic, request).await,
+        Err(err) => Err(JellyfinError::Request(err)),
+    };
     let response = match response {
         Ok(res) => res,
-        Err(err) => {
+        Err(JellyfinError::Request(err)) => {
             return HttpResponse::BadGateway().json(ApiResponse::<()>::err(format!(
                 "Proxy request failed: {err}"
             )))
         }
+        Err(err) => return err.error_response(),
     };
 
     let status = StatusCode::from_u16(response.status().as_u16())
@@ -105,17 +160,16 @@ async fn proxy_request(
         .get("accept-ranges")
         .and_then(|val| val.to_str().ok())
         .map(|val| val.to_string());
-    let bytes = match response.bytes().await {
-        Ok(data) => data,
-        Err(err) => {
-            return HttpResponse::BadGateway().json(ApiResponse::<()>::err(format!(
-                "Failed to read Jellyfin response: {err}"
-            )))
-        }
-    };
-
     let mut builder = HttpResponse::build(status);
-    if let Some(content_type) = content_type {
+    if status.is_success() {
+        if invalidates_cache {
+            state.metadata.invalidate_user(&session);
+        }
+        if let Some(progress) = progress {
+            state.shutdown.record_progress(&session, progress);
+        }
+    }
+    if let Some(content_type) = content_type.clone() {
         builder.insert_header(("content-type", content_type));
     }
     if let Some(content_length) = content_length {
@@ -128,5 +182,62 @@ async fn proxy_request(
         builder.insert_header(("accept-ranges", accept_ranges));
     }
 
+    // Media is relayed as it arrives instead of being buffered whole. The guard and ticket
+    // live as long as the body stream, so both are released when the client leaves.
+    if traffic == Traffic::Streaming {
+        let guard = state.metrics.stream_started();
+        let stream = response.bytes_stream().map(move |chunk| {
+            let _ = &ticket;
+            if let Ok(bytes) = &chunk {
+                guard.add_bytes(bytes.len());
+            }
+            chunk.map_err(actix_web::error::ErrorBadGateway)
+        });
+        return builder.streaming(stream);
+    }
+
+    let bytes = match response.bytes().await {
+        Ok(data) => data,
+        Err(err) => {
+            return HttpResponse::BadGateway().json(ApiResponse::<()>::err(format!(
+                "Failed to read Jellyfin response: {err}"
+            )))
+        }
+    };
+    let cache_key = cache_key.filter(|_| status.is_success());
+    if let (Some(key), Some(ttl)) = (cache_key, cache_ttl) {
+        state.metadata.put(key, ttl, bytes.clone(), content_type);
+        builder.insert_header(("x-cache", "MISS"));
+    }
+
     builder.body(bytes)
 }
+
+/// Playback start and progress reports, whose position a stop report sent at shutdown reuses.
+fn is_progress_report(method: &reqwest::Method, tail: &str) -> bool {
+    let path = tail.trim_matches('/');
+    method == reqwest::Method::POST
+        && (path.eq_ignore_ascii_case("Sessions/Playing")
+            || path.eq_ignore_ascii_case("Sessions/Playing/Progress"))
+}
+
+/// The item a streamed path plays, such as `<id>` in `Videos/<id>/stream`. Live TV streams and
+/// other ranged reads name none, and are not reported to Jellyfin at shutdown.
+fn streamed_item(tail: &str) -> &str {
+    let mut segments = tail
+        .split('?')
+        .next()
+        .unwrap_or("")
+        .split('/')
+        .filter(|segment| !segment.is_empty());
+    match (segments.next(), segments.next()) {
+        (Some(kind), Some(item_id))
+            if ["videos", "audio", "items"]
+                .iter()
+                .any(|streamed| kind.eq_ignore_ascii_case(streamed)) =>
+        {
+            item_id
+        }
+        _ => "",
+    }
+}
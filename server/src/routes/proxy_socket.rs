@@ -0,0 +1,519 @@
+//
+//  media-savant-api
+//  routes/proxy_socket.rs
+//
+
+use std::sync::Arc;
+
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{
+    hash_key, verify_handshake, CloseCode, CloseReason, Codec, Frame, Item, Message, ProtocolError,
+};
+use actix_web::guard::GuardContext;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use bytes::{Bytes, BytesMut};
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Instant};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::{Message as UpstreamMessage, Utf8Bytes};
+use tokio_tungstenite::{
+    connect_async_tls_with_config, Connector, MaybeTlsStream, WebSocketStream,
+};
+use tracing::warn;
+
+use crate::config::ProxyConfig;
+use crate::jellyfin::websocket_url;
+use crate::models::ApiResponse;
+use crate::routes::auth::{build_token_header, require_session};
+use crate::state::AppState;
+use crate::telemetry::propagate;
+
+type UpstreamSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Frames queued for the browser before the pump waits for it to catch up.
+const CLIENT_BUFFER: usize = 32;
+
+/// Matches browser requests asking to upgrade to a WebSocket.
+pub fn is_websocket_upgrade(ctx: &GuardContext) -> bool {
+    ctx.head()
+        .headers()
+        .get(header::UPGRADE)
+        .and_then(|val| val.to_str().ok())
+        .is_some_and(|val| val.to_ascii_lowercase().contains("websocket"))
+}
+
+/// Bridges a browser WebSocket to the same path on Jellyfin.
+///
+/// The upstream connection authenticates with the session's token in a header; any `api_key`
+/// the browser put in the query is dropped, so tokens never need to reach the browser.
+pub async fn passthrough(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> HttpResponse {
+    if let Err(err) = verify_handshake(req.head()) {
+        return err.error_response();
+    }
+    let Some(accept) = req
+        .headers()
+        .get(header::SEC_WEBSOCKET_KEY)
+        .map(|key| hash_key(key.as_bytes()))
+    else {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("Missing WebSocket key"));
+    };
+
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let mut path = req.match_info().query("tail").to_string();
+    let query = strip_api_key(req.query_string());
+    if !query.is_empty() {
+        path.push('?');
+        path.push_str(&query);
+    }
+    let target = websocket_url(&session.server_url, &path);
+
+    let tls = state.transport.tls_config(&session.server_url);
+    let upstream = match connect(&target, &build_token_header(&state, &session), tls).await {
+        Ok(upstream) => upstream,
+        Err(err) => {
+            return HttpResponse::BadGateway().json(ApiResponse::<()>::err(format!(
+                "Failed to open Jellyfin WebSocket: {err}"
+            )));
+        }
+    };
+
+    let (client_tx, client_rx) = mpsc::channel::<Bytes>(CLIENT_BUFFER);
+    actix_web::rt::spawn(pump(
+        payload,
+        upstream,
+        client_tx,
+        state.config().proxy.clone(),
+    ));
+
+    let body = futures_util::stream::unfold(client_rx, |mut client_rx| async move {
+        let frame = client_rx.recv().await?;
+        Some((Ok::<_, actix_web::Error>(frame), client_rx))
+    });
+    HttpResponse::SwitchingProtocols()
+        .upgrade("websocket")
+        .insert_header((
+            header::SEC_WEBSOCKET_ACCEPT,
+            HeaderValue::from_bytes(&accept).expect("accept key is base64"),
+        ))
+        .streaming(body)
+}
+
+async fn connect(
+    target: &str,
+    auth_header: &str,
+    tls: Arc<rustls::ClientConfig>,
+) -> Result<UpstreamSocket, Box<dyn std::error::Error>> {
+    let mut request = target.into_client_request()?;
+    request
+        .headers_mut()
+        .insert("X-Emby-Authorization", auth_header.parse()?);
+    propagate(request.headers_mut());
+    let (socket, _) =
+        connect_async_tls_with_config(request, None, false, Some(Connector::Rustls(tls))).await?;
+    Ok(socket)
+}
+
+fn strip_api_key(query: &str) -> String {
+    query
+        .split('&')
+        .filter(|pair| {
+            let name = pair.split('=').next().unwrap_or("");
+            !name.eq_ignore_ascii_case("api_key") && !name.eq_ignore_ascii_case("apikey")
+        })
+        .filter(|pair| !pair.is_empty())
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// How the bridge ended, and what each side should be told.
+enum Shutdown {
+    /// The browser sent a close frame; echo it and pass it on.
+    Client(Option<CloseReason>),
+    /// Jellyfin sent a close frame; pass it on to the browser.
+    Upstream(Option<CloseReason>),
+    /// The browser went away without a close frame.
+    ClientGone,
+    /// The browser broke the protocol or sent too large a message; it is told why.
+    Rejected(CloseReason),
+    Idle,
+    Error(String),
+}
+
+/// Pumps frames both ways until either side closes or the connection goes idle.
+async fn pump(
+    mut payload: web::Payload,
+    upstream: UpstreamSocket,
+    client_tx: mpsc::Sender<Bytes>,
+    config: ProxyConfig,
+) {
+    let (mut upstream_sink, mut upstream_stream) = upstream.split();
+    let mut client = ClientSide::new(client_tx, config.ws_max_frame_bytes);
+    let idle = sleep(config.ws_idle_timeout);
+    tokio::pin!(idle);
+
+    let shutdown = loop {
+        tokio::select! {
+            chunk = payload.next() => {
+                let chunk = match chunk {
+                    Some(Ok(chunk)) => chunk,
+                    Some(Err(err)) => break Shutdown::Error(err.to_string()),
+                    None => break Shutdown::ClientGone,
+                };
+                idle.as_mut().reset(Instant::now() + config.ws_idle_timeout);
+
+                let frames = match client.decode(&chunk) {
+                    Ok(frames) => frames,
+                    Err(reason) => break Shutdown::Rejected(reason),
+                };
+                let mut closed = None;
+                for frame in frames {
+                    match frame {
+                        ClientFrame::Forward(message) => {
+                            if let Err(err) = upstream_sink.send(message).await {
+                                closed = Some(Shutdown::Error(err.to_string()));
+                                break;
+                            }
+                        }
+                        ClientFrame::Ping(data) => {
+                            if !client.send(Message::Pong(data)).await {
+                                closed = Some(Shutdown::ClientGone);
+                                break;
+                            }
+                        }
+                        ClientFrame::Close(reason) => {
+                            closed = Some(Shutdown::Client(reason));
+                            break;
+                        }
+                    }
+                }
+                if let Some(shutdown) = closed {
+                    break shutdown;
+                }
+            }
+            message = upstream_stream.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(err)) => break Shutdown::Error(err.to_string()),
+                    None => break Shutdown::Upstream(None),
+                };
+                idle.as_mut().reset(Instant::now() + config.ws_idle_timeout);
+
+                let forwarded = match message {
+                    UpstreamMessage::Text(text) => Message::Text(text.as_str().into()),
+                    UpstreamMessage::Binary(data) => Message::Binary(data),
+                    UpstreamMessage::Close(frame) => {
+                        break Shutdown::Upstream(frame.map(|frame| CloseReason {
+                            code: CloseCode::from(u16::from(frame.code)),
+                            description: Some(frame.reason.to_string()),
+                        }));
+                    }
+                    // tungstenite answers upstream pings itself.
+                    _ => continue,
+                };
+                if !client.send(forwarded).await {
+                    break Shutdown::ClientGone;
+                }
+            }
+            _ = &mut idle => break Shutdown::Idle,
+        }
+    };
+
+    let (to_client, to_upstream) = match shutdown {
+        Shutdown::Client(reason) => (Some(reason.clone()), Some(reason)),
+        Shutdown::Upstream(reason) => (Some(reason), None),
+        Shutdown::ClientGone => (None, Some(Some(CloseCode::Away.into()))),
+        Shutdown::Rejected(reason) => {
+            warn!(
+                "Closing proxied WebSocket: {}",
+                reason.description.as_deref().unwrap_or("protocol error")
+            );
+            (Some(Some(reason)), Some(Some(CloseCode::Away.into())))
+        }
+        Shutdown::Idle => {
+            let reason = CloseReason {
+                code: CloseCode::Away,
+                description: Some("Idle timeout".to_string()),
+            };
+            (Some(Some(reason.clone())), Some(Some(reason)))
+        }
+        Shutdown::Error(err) => {
+            warn!("Proxied WebSocket failed: {err}");
+            let reason = CloseReason::from(CloseCode::Error);
+            (Some(Some(reason.clone())), Some(Some(reason)))
+        }
+    };
+
+    if let Some(reason) = to_upstream {
+        let frame = reason.map(|reason| CloseFrame {
+            code: u16::from(reason.code).into(),
+            reason: Utf8Bytes::from(reason.description.unwrap_or_default()),
+        });
+        let _ = upstream_sink.send(UpstreamMessage::Close(frame)).await;
+    }
+    let _ = upstream_sink.close().await;
+    if let Some(reason) = to_client {
+        client.send(Message::Close(reason)).await;
+    }
+}
+
+#[derive(Debug)]
+enum ClientFrame {
+    Forward(UpstreamMessage),
+    Ping(Bytes),
+    Close(Option<CloseReason>),
+}
+
+/// Browser half of the bridge: decodes its frames and encodes ours.
+struct ClientSide {
+    codec: Codec,
+    read_buf: BytesMut,
+    /// A fragmented message being reassembled, and whether it is text.
+    fragments: Option<(bool, BytesMut)>,
+    /// Limit for single frames and reassembled messages alike.
+    max_message_bytes: usize,
+    tx: mpsc::Sender<Bytes>,
+}
+
+impl ClientSide {
+    fn new(tx: mpsc::Sender<Bytes>, max_frame_bytes: usize) -> Self {
+        Self {
+            codec: Codec::new().max_size(max_frame_bytes),
+            read_buf: BytesMut::new(),
+            fragments: None,
+            max_message_bytes: max_frame_bytes,
+            tx,
+        }
+    }
+
+    /// Decodes whatever complete frames the chunk finishes, or the close frame to reject the
+    /// browser with.
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<ClientFrame>, CloseReason> {
+        self.read_buf.extend_from_slice(chunk);
+
+        let mut frames = Vec::new();
+        while let Some(frame) = self.codec.decode(&mut self.read_buf).map_err(|err| {
+            let code = match err {
+                ProtocolError::Overflow => CloseCode::Size,
+                _ => CloseCode::Protocol,
+            };
+            close_reason(code, err.to_string())
+        })? {
+            let frame = match frame {
+                Frame::Text(data) => Some(text_message(data)?),
+                Frame::Binary(data) => Some(ClientFrame::Forward(UpstreamMessage::Binary(data))),
+                Frame::Ping(data) => Some(ClientFrame::Ping(data)),
+                Frame::Pong(_) => None,
+                Frame::Close(reason) => Some(ClientFrame::Close(reason)),
+                Frame::Continuation(item) => self.continuation(item)?,
+            };
+            frames.extend(frame);
+        }
+        Ok(frames)
+    }
+
+    fn continuation(&mut self, item: Item) -> Result<Option<ClientFrame>, CloseReason> {
+        let unexpected = || close_reason(CloseCode::Protocol, "Unexpected continuation");
+        match item {
+            Item::FirstText(_) | Item::FirstBinary(_) if self.fragments.is_some() => {
+                return Err(close_reason(
+                    CloseCode::Protocol,
+                    "New message started inside a fragmented one",
+                ));
+            }
+            Item::FirstText(data) => self.fragments = Some((true, BytesMut::from(&data[..]))),
+            Item::FirstBinary(data) => self.fragments = Some((false, BytesMut::from(&data[..]))),
+            Item::Continue(data) => {
+                let (_, buffer) = self.fragments.as_mut().ok_or_else(unexpected)?;
+                append_fragment(buffer, &data, self.max_message_bytes)?;
+            }
+            Item::Last(data) => {
+                let (is_text, mut buffer) = self.fragments.take().ok_or_else(unexpected)?;
+                append_fragment(&mut buffer, &data, self.max_message_bytes)?;
+                let data = buffer.freeze();
+                return Ok(Some(if is_text {
+                    text_message(data)?
+                } else {
+                    ClientFrame::Forward(UpstreamMessage::Binary(data))
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Encodes and queues a frame for the browser; false once the browser has gone.
+    async fn send(&mut self, message: Message) -> bool {
+        let mut buf = BytesMut::new();
+        if self.codec.encode(message, &mut buf).is_err() {
+            return false;
+        }
+        self.tx.send(buf.freeze()).await.is_ok()
+    }
+}
+
+/// Adds a fragment to a message being reassembled, as long as it stays within `max_bytes`.
+fn append_fragment(
+    buffer: &mut BytesMut,
+    data: &[u8],
+    max_bytes: usize,
+) -> Result<(), CloseReason> {
+    if buffer.len() + data.len() > max_bytes {
+        return Err(close_reason(
+            CloseCode::Size,
+            format!("Message exceeds {max_bytes} bytes"),
+        ));
+    }
+    buffer.extend_from_slice(data);
+    Ok(())
+}
+
+fn text_message(data: Bytes) -> Result<ClientFrame, CloseReason> {
+    let text = Utf8Bytes::try_from(data)
+        .map_err(|err| close_reason(CloseCode::Invalid, err.to_string()))?;
+    Ok(ClientFrame::Forward(UpstreamMessage::Text(text)))
+}
+
+fn close_reason(code: CloseCode, description: impl Into<String>) -> CloseReason {
+    CloseReason {
+        code,
+        description: Some(description.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_http::ws::{OpCode, Parser};
+
+    const MAX_BYTES: usize = 16;
+
+    fn client() -> ClientSide {
+        let (tx, _) = mpsc::channel(1);
+        ClientSide::new(tx, MAX_BYTES)
+    }
+
+    /// Encodes messages the way a browser sends them: masked, as a client.
+    fn from_browser(messages: Vec<Message>) -> BytesMut {
+        let mut codec = Codec::new().client_mode();
+        let mut buf = BytesMut::new();
+        for message in messages {
+            codec.encode(message, &mut buf).unwrap();
+        }
+        buf
+    }
+
+    /// Writes raw masked frames, including sequences a well-behaved encoder refuses to produce.
+    fn raw_frames(frames: &[(OpCode, bool, &[u8])]) -> BytesMut {
+        let mut buf = BytesMut::new();
+        for &(op, fin, payload) in frames {
+            Parser::write_message(&mut buf, payload, op, fin, true);
+        }
+        buf
+    }
+
+    fn forwarded_text(frame: &ClientFrame) -> &str {
+        match frame {
+            ClientFrame::Forward(UpstreamMessage::Text(text)) => text.as_str(),
+            other => panic!("expected forwarded text, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn forwards_whole_messages() {
+        let chunk = from_browser(vec![
+            Message::Text("hello".into()),
+            Message::Ping(Bytes::from_static(b"ping")),
+        ]);
+        let frames = client().decode(&chunk).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(forwarded_text(&frames[0]), "hello");
+        assert!(matches!(&frames[1], ClientFrame::Ping(data) if data == "ping"));
+    }
+
+    #[test]
+    fn reassembles_fragmented_messages() {
+        let chunk = from_browser(vec![
+            Message::Continuation(Item::FirstText(Bytes::from_static(b"hel"))),
+            Message::Continuation(Item::Continue(Bytes::from_static(b"lo "))),
+            Message::Continuation(Item::Last(Bytes::from_static(b"there"))),
+            Message::Continuation(Item::FirstBinary(Bytes::from_static(&[1, 2]))),
+            Message::Continuation(Item::Last(Bytes::from_static(&[3]))),
+        ]);
+        let frames = client().decode(&chunk).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(forwarded_text(&frames[0]), "hello there");
+        assert!(matches!(
+            &frames[1],
+            ClientFrame::Forward(UpstreamMessage::Binary(data)) if data[..] == [1, 2, 3]
+        ));
+    }
+
+    #[test]
+    fn waits_for_frames_split_across_chunks() {
+        let chunk = from_browser(vec![
+            Message::Continuation(Item::FirstText(Bytes::from_static(b"hel"))),
+            Message::Continuation(Item::Last(Bytes::from_static(b"lo"))),
+        ]);
+        let mut client = client();
+        let (head, tail) = chunk.split_at(4);
+        assert!(client.decode(head).unwrap().is_empty());
+        let frames = client.decode(tail).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(forwarded_text(&frames[0]), "hello");
+    }
+
+    #[test]
+    fn rejects_oversized_frames() {
+        let chunk = from_browser(vec![Message::Binary(Bytes::from(vec![0; MAX_BYTES + 1]))]);
+        let reason = client().decode(&chunk).unwrap_err();
+        assert_eq!(reason.code, CloseCode::Size);
+    }
+
+    #[test]
+    fn rejects_oversized_reassembled_messages() {
+        // Each fragment fits, but together they exceed the limit.
+        let chunk = from_browser(vec![
+            Message::Continuation(Item::FirstBinary(Bytes::from(vec![0; 10]))),
+            Message::Continuation(Item::Continue(Bytes::from(vec![0; 10]))),
+        ]);
+        let reason = client().decode(&chunk).unwrap_err();
+        assert_eq!(reason.code, CloseCode::Size);
+    }
+
+    #[test]
+    fn rejects_a_message_started_inside_another() {
+        let chunk = raw_frames(&[(OpCode::Text, false, b"one"), (OpCode::Text, false, b"two")]);
+        let reason = client().decode(&chunk).unwrap_err();
+        assert_eq!(reason.code, CloseCode::Protocol);
+    }
+
+    #[test]
+    fn rejects_unexpected_continuations() {
+        for fin in [false, true] {
+            let chunk = raw_frames(&[(OpCode::Continue, fin, b"more")]);
+            let reason = client().decode(&chunk).unwrap_err();
+            assert_eq!(reason.code, CloseCode::Protocol);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_utf8_text() {
+        let chunk = from_browser(vec![
+            Message::Continuation(Item::FirstText(Bytes::from_static(&[0xe2, 0x82]))),
+            Message::Continuation(Item::Last(Bytes::from_static(&[0x28]))),
+        ]);
+        let reason = client().decode(&chunk).unwrap_err();
+        assert_eq!(reason.code, CloseCode::Invalid);
+    }
+}
@@ -0,0 +1,320 @@
+//
+//  media-savant-api
+//  routes/queue.rs
+//
+
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use redis::aio::MultiplexedConnection;
+use uuid::Uuid;
+
+use crate::jellyfin::{JellyfinClient, JellyfinError};
+use crate::metrics::Metrics;
+use crate::models::{
+    ApiResponse, CreateQueueRequest, JellyfinItem, JellyfinItemsResponse, JumpRequest, PlayQueue,
+    QueueEntry, QueueError, QueueSourceType, ReorderRequest, SessionData, ShuffleRequest,
+};
+use crate::routes::auth::require_session;
+use crate::routes::library::LIST_FIELDS;
+use crate::state::AppState;
+
+/// Queues are dropped after a month without changes.
+const QUEUE_TTL_SECONDS: u64 = 30 * 24 * 60 * 60;
+const MAX_QUEUE_ITEMS: &str = "1000";
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/queue")
+            .service(get_queue)
+            .service(create_queue)
+            .service(clear_queue)
+            .service(shuffle_queue)
+            .service(reorder_queue)
+            .service(remove_entry)
+            .service(jump_to_entry)
+            .service(next_entry)
+            .service(previous_entry),
+    );
+}
+
+#[get("")]
+async fn get_queue(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let mut conn = state.redis.lock().await;
+    match load_queue(&state.metrics, &mut conn, &queue_key(&session)).await {
+        Ok(queue) => HttpResponse::Ok().json(ApiResponse::ok(queue)),
+        Err(err) => HttpResponse::InternalServerError().json(ApiResponse::<()>::err(format!(
+            "Failed to load queue: {err}"
+        ))),
+    }
+}
+
+#[post("")]
+async fn create_queue(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Json<CreateQueueRequest>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let items = match resolve_items(&client, payload.source_type, &payload.source_id).await {
+        Ok(items) => items,
+        Err(err) => return err.error_response(),
+    };
+    if items.is_empty() {
+        return HttpResponse::UnprocessableEntity().json(ApiResponse::<()>::err(
+            "Nothing playable in the queue source",
+        ));
+    }
+
+    let entries = items.into_iter().map(QueueEntry::from).collect();
+    let queue = PlayQueue::new(&payload, entries);
+
+    let key = queue_key(&session);
+    let _profile = state.queue_locks.lock(&key).await;
+    let mut conn = state.redis.lock().await;
+    if let Err(err) = save_queue(&state.metrics, &mut conn, &key, &queue).await {
+        return HttpResponse::InternalServerError().json(ApiResponse::<()>::err(format!(
+            "Failed to save queue: {err}"
+        )));
+    }
+
+    HttpResponse::Ok().json(ApiResponse::ok(queue))
+}
+
+#[delete("")]
+async fn clear_queue(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let key = queue_key(&session);
+    let _profile = state.queue_locks.lock(&key).await;
+    let mut conn = state.redis.lock().await;
+    let _timer = state.metrics.redis_timer("DEL");
+    let result = redis::cmd("DEL")
+        .arg(&key)
+        .query_async::<_, ()>(&mut *conn)
+        .await;
+    match result {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::ok(serde_json::json!({ "cleared": true }))),
+        Err(err) => HttpResponse::InternalServerError().json(ApiResponse::<()>::err(format!(
+            "Failed to clear queue: {err}"
+        ))),
+    }
+}
+
+#[post("/shuffle")]
+async fn shuffle_queue(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Json<ShuffleRequest>,
+) -> impl Responder {
+    update_queue(&state, &req, |queue| {
+        queue.set_shuffle(payload.enabled);
+        Ok(())
+    })
+    .await
+}
+
+#[post("/reorder")]
+async fn reorder_queue(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Json<ReorderRequest>,
+) -> impl Responder {
+    update_queue(&state, &req, |queue| {
+        queue.reorder(payload.entry_id, payload.to_index)
+    })
+    .await
+}
+
+#[delete("/entries/{entry_id}")]
+async fn remove_entry(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let entry_id = path.into_inner();
+    update_queue(&state, &req, |queue| queue.remove(entry_id)).await
+}
+
+#[post("/jump")]
+async fn jump_to_entry(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    payload: web::Json<JumpRequest>,
+) -> impl Responder {
+    update_queue(&state, &req, |queue| queue.jump(payload.entry_id)).await
+}
+
+#[post("/next")]
+async fn next_entry(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    update_queue(&state, &req, PlayQueue::next).await
+}
+
+#[post("/previous")]
+async fn previous_entry(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    update_queue(&state, &req, PlayQueue::previous).await
+}
+
+/// Applies `change` to the caller's queue under the profile's queue lock. The shared Redis
+/// connection is only held for the load and the save themselves.
+async fn update_queue(
+    state: &AppState,
+    req: &HttpRequest,
+    change: impl FnOnce(&mut PlayQueue) -> Result<(), QueueError>,
+) -> HttpResponse {
+    let session = match require_session(state, req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let key = queue_key(&session);
+    let _profile = state.queue_locks.lock(&key).await;
+
+    let loaded = {
+        let mut conn = state.redis.lock().await;
+        load_queue(&state.metrics, &mut conn, &key).await
+    };
+    let mut queue = match loaded {
+        Ok(Some(queue)) => queue,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ApiResponse::<()>::err("No active queue"))
+        }
+        Err(err) => {
+            return HttpResponse::InternalServerError().json(ApiResponse::<()>::err(format!(
+                "Failed to load queue: {err}"
+            )))
+        }
+    };
+
+    if let Err(err) = change(&mut queue) {
+        let mut response = match err {
+            QueueError::EntryNotFound => HttpResponse::NotFound(),
+            QueueError::EndOfQueue | QueueError::StartOfQueue => HttpResponse::Conflict(),
+        };
+        return response.json(ApiResponse::<()>::err(err.to_string()));
+    }
+    queue.revision += 1;
+
+    let mut conn = state.redis.lock().await;
+    if let Err(err) = save_queue(&state.metrics, &mut conn, &key, &queue).await {
+        return HttpResponse::InternalServerError().json(ApiResponse::<()>::err(format!(
+            "Failed to save queue: {err}"
+        )));
+    }
+
+    HttpResponse::Ok().json(ApiResponse::ok(queue))
+}
+
+/// Queues belong to the Jellyfin profile rather than the browser session, so every client
+/// signed into the same user shares one.
+fn queue_key(session: &SessionData) -> String {
+    format!(
+        "queue:{}:{}",
+        session.server_url.trim_end_matches('/'),
+        session.user_id
+    )
+}
+
+async fn resolve_items(
+    client: &JellyfinClient<'_>,
+    source_type: QueueSourceType,
+    source_id: &str,
+) -> Result<Vec<JellyfinItem>, JellyfinError> {
+    let user_id = client.user_id().to_string();
+    let response = match source_type {
+        QueueSourceType::Item => {
+            let path = format!("/Users/{user_id}/Items/{source_id}");
+            let item = client
+                .get_json::<JellyfinItem>(&path, &[("Fields", LIST_FIELDS.to_string())])
+                .await?;
+            return Ok(vec![item]);
+        }
+        QueueSourceType::Season => {
+            let path = format!("/Users/{user_id}/Items");
+            let params = [
+                ("ParentId", source_id.to_string()),
+                ("IncludeItemTypes", "Episode".to_string()),
+                (
+                    "SortBy",
+                    "ParentIndexNumber,IndexNumber,SortName".to_string(),
+                ),
+                ("Fields", LIST_FIELDS.to_string()),
+                ("Limit", MAX_QUEUE_ITEMS.to_string()),
+            ];
+            client
+                .get_json::<JellyfinItemsResponse>(&path, &params)
+                .await?
+        }
+        QueueSourceType::Series => {
+            let path = format!("/Shows/{source_id}/Episodes");
+            let params = [
+                ("UserId", user_id),
+                ("Fields", LIST_FIELDS.to_string()),
+                ("Limit", MAX_QUEUE_ITEMS.to_string()),
+            ];
+            client
+                .get_json::<JellyfinItemsResponse>(&path, &params)
+                .await?
+        }
+        QueueSourceType::Playlist => {
+            let path = format!("/Playlists/{source_id}/Items");
+            let params = [
+                ("UserId", user_id),
+                ("Fields", LIST_FIELDS.to_string()),
+                ("Limit", MAX_QUEUE_ITEMS.to_string()),
+            ];
+            client
+                .get_json::<JellyfinItemsResponse>(&path, &params)
+                .await?
+        }
+    };
+
+    Ok(response.items)
+}
+
+async fn load_queue(
+    metrics: &Metrics,
+    conn: &mut MultiplexedConnection,
+    key: &str,
+) -> Result<Option<PlayQueue>, Box<dyn std::error::Error>> {
+    let data: Option<String> = {
+        let _timer = metrics.redis_timer("GET");
+        redis::cmd("GET").arg(key).query_async(conn).await?
+    };
+
+    if let Some(value) = data {
+        let queue = serde_json::from_str::<PlayQueue>(&value)?;
+        Ok(Some(queue))
+    } else {
+        Ok(None)
+    }
+}
+
+async fn save_queue(
+    metrics: &Metrics,
+    conn: &mut MultiplexedConnection,
+    key: &str,
+    queue: &PlayQueue,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let value = serde_json::to_string(queue)?;
+
+    let _timer = metrics.redis_timer("SET");
+    redis::cmd("SET")
+        .arg(key)
+        .arg(value)
+        .arg("EX")
+        .arg(QUEUE_TTL_SECONDS)
+        .query_async::<_, ()>(conn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,259 @@
+//
+//  media-savant-api
+//  routes/remote.rs
+//
+
+use actix_web::http::StatusCode;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde_json::json;
+
+use crate::jellyfin::{JellyfinClient, JellyfinError};
+use crate::models::{
+    ApiResponse, JellyfinSessionInfo, PlayItemsRequest, PlaystateCommand, PlaystateRequest,
+    RemoteSession, VolumeRequest,
+};
+use crate::routes::auth::require_session;
+use crate::state::AppState;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/remote")
+            .service(list_sessions)
+            .service(send_playstate)
+            .service(set_volume)
+            .service(play_items),
+    );
+}
+
+#[get("/sessions")]
+async fn list_sessions(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    match controllable_sessions(&client).await {
+        Ok(sessions) => {
+            let sessions: Vec<RemoteSession> = sessions
+                .into_iter()
+                .map(|target| RemoteSession::from_jellyfin(target, &session.device_id))
+                .collect();
+            HttpResponse::Ok().json(ApiResponse::ok(sessions))
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[post("/sessions/{session_id}/playstate")]
+async fn send_playstate(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<PlaystateRequest>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let mut params = Vec::new();
+    if let PlaystateCommand::Seek = body.command {
+        let Some(position_ticks) = body.position_ticks else {
+            return HttpResponse::BadRequest()
+                .json(ApiResponse::<()>::err("position_ticks is required to seek"));
+        };
+        params.push(("seekPositionTicks", position_ticks.max(0).to_string()));
+    }
+
+    let target = match find_target(&client, &path).await {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+    let command_path = format!(
+        "/Sessions/{}/Playing/{}",
+        target.id,
+        body.command.as_jellyfin()
+    );
+    command_sent(client.post(&command_path, &params).await)
+}
+
+#[post("/sessions/{session_id}/volume")]
+async fn set_volume(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<VolumeRequest>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    if body.level.is_none() && body.muted.is_none() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(
+            "Provide a volume level or muted flag",
+        ));
+    }
+    if body.level.is_some_and(|level| level > 100) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err(
+            "Volume level must be between 0 and 100",
+        ));
+    }
+
+    let target = match find_target(&client, &path).await {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+
+    if let Some(level) = body.level {
+        let command = json!({
+            "Name": "SetVolume",
+            "Arguments": { "Volume": level.to_string() },
+        });
+        let command_path = format!("/Sessions/{}/Command", target.id);
+        if let Err(err) = client.post_json(&command_path, &[], &command).await {
+            return err.error_response();
+        }
+    }
+    if let Some(muted) = body.muted {
+        let name = if muted { "Mute" } else { "Unmute" };
+        let command_path = format!("/Sessions/{}/Command/{name}", target.id);
+        if let Err(err) = client.post(&command_path, &[]).await {
+            return err.error_response();
+        }
+    }
+
+    command_sent(Ok(()))
+}
+
+#[post("/sessions/{session_id}/play")]
+async fn play_items(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<PlayItemsRequest>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    if body.item_ids.is_empty() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("item_ids is empty"));
+    }
+
+    let target = match find_target(&client, &path).await {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+
+    let mut params = vec![
+        ("playCommand", body.mode.as_jellyfin().to_string()),
+        ("itemIds", body.item_ids.join(",")),
+    ];
+    if let Some(ticks) = body.start_position_ticks {
+        params.push(("startPositionTicks", ticks.max(0).to_string()));
+    }
+    if let Some(index) = body.start_index {
+        params.push(("startIndex", index.to_string()));
+    }
+
+    let command_path = format!("/Sessions/{}/Playing", target.id);
+    command_sent(client.post(&command_path, &params).await)
+}
+
+/// Sessions the signed-in user may control, as Jellyfin sees it.
+async fn controllable_sessions(
+    client: &JellyfinClient<'_>,
+) -> Result<Vec<JellyfinSessionInfo>, JellyfinError> {
+    client
+        .get_json("/Sessions", &controllable_by(client.user_id()))
+        .await
+}
+
+fn controllable_by(user_id: &str) -> [(&'static str, String); 1] {
+    [("ControllableByUserId", user_id.to_string())]
+}
+
+/// Resolves the target session, refusing any the user cannot control so a session id alone
+/// is never enough to drive someone else's player.
+async fn find_target(
+    client: &JellyfinClient<'_>,
+    session_id: &str,
+) -> Result<JellyfinSessionInfo, HttpResponse> {
+    let sessions = controllable_sessions(client)
+        .await
+        .map_err(|err| err.error_response())?;
+    select_target(sessions, session_id).map_err(|(status, message)| {
+        HttpResponse::build(status).json(ApiResponse::<()>::err(message))
+    })
+}
+
+fn select_target(
+    sessions: Vec<JellyfinSessionInfo>,
+    session_id: &str,
+) -> Result<JellyfinSessionInfo, (StatusCode, &'static str)> {
+    let Some(target) = sessions.into_iter().find(|target| target.id == session_id) else {
+        return Err((StatusCode::NOT_FOUND, "Session not found"));
+    };
+    if !target.supports_remote_control {
+        return Err((
+            StatusCode::CONFLICT,
+            "Session does not support remote control",
+        ));
+    }
+    Ok(target)
+}
+
+fn command_sent(result: Result<(), JellyfinError>) -> HttpResponse {
+    match result {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "sent": true }))),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sessions() -> Vec<JellyfinSessionInfo> {
+        serde_json::from_value(json!([
+            { "Id": "tv", "DeviceId": "tv-device", "SupportsRemoteControl": true },
+            { "Id": "phone", "DeviceId": "phone-device", "SupportsRemoteControl": true },
+            { "Id": "dlna", "DeviceId": "dlna-device" },
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn asks_only_for_sessions_the_user_controls() {
+        let [(name, value)] = controllable_by("user-1");
+        assert_eq!(name, "ControllableByUserId");
+        assert_eq!(value, "user-1");
+    }
+
+    #[test]
+    fn targets_only_the_requested_session() {
+        let target = select_target(sessions(), "phone").unwrap();
+        assert_eq!(target.id, "phone");
+        assert_eq!(target.device_id.as_deref(), Some("phone-device"));
+    }
+
+    #[test]
+    fn refuses_sessions_outside_the_controllable_list() {
+        // Jellyfin left this session out of the controllable list, so it is not found even
+        // though the id exists on the server.
+        let (status, _) = select_target(sessions(), "someone-elses-tv").unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn refuses_sessions_without_remote_control() {
+        let (status, _) = select_target(sessions(), "dlna").unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,330 @@
+//
+//  media-savant-api
+//  routes/search.rs
+//
+
+use std::collections::HashMap;
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, ResponseError};
+
+use crate::jellyfin::JellyfinClient;
+use crate::models::{
+    ApiResponse, JellyfinItemsResponse, JellyfinSearchHints, SearchGroup, SearchHit, SearchKind,
+    SearchQuery, SearchResults,
+};
+use crate::routes::auth::require_session;
+use crate::routes::library::LIST_FIELDS;
+use crate::state::AppState;
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+/// Candidates fetched per upstream call before ranking.
+const CANDIDATE_LIMIT: &str = "200";
+/// Score given to anything Jellyfin matched that our own ranking cannot explain, for example
+/// a match on the original title.
+const UPSTREAM_MATCH_SCORE: u32 = 100;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(search);
+}
+
+#[get("/search")]
+async fn search(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<SearchQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let query = query.into_inner();
+    let term = query.q.trim().to_string();
+    if term.is_empty() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("Query must not be empty"));
+    }
+
+    let kinds: Vec<SearchKind> = match query.types.as_deref() {
+        Some(types) => types.split(',').filter_map(SearchKind::parse).collect(),
+        None => SearchKind::ALL.to_vec(),
+    };
+    if kinds.is_empty() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("No valid item types"));
+    }
+    let item_kinds: Vec<&str> = kinds
+        .iter()
+        .filter(|kind| **kind != SearchKind::Person)
+        .map(|kind| kind.as_jellyfin())
+        .collect();
+
+    let mut hint_params = vec![
+        ("searchTerm", term.clone()),
+        ("UserId", client.user_id().to_string()),
+        (
+            "IncludeItemTypes",
+            kinds
+                .iter()
+                .map(|kind| kind.as_jellyfin())
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        ("Limit", CANDIDATE_LIMIT.to_string()),
+    ];
+    let mut item_params = vec![
+        ("Recursive", "true".to_string()),
+        ("IncludeItemTypes", item_kinds.join(",")),
+        ("Fields", LIST_FIELDS.to_string()),
+        ("Limit", CANDIDATE_LIMIT.to_string()),
+    ];
+    if let Some(library_id) = &query.library_id {
+        hint_params.push(("ParentId", library_id.clone()));
+        item_params.push(("ParentId", library_id.clone()));
+    }
+
+    // Jellyfin's own search is substring based and misses typos, so also pull everything whose
+    // name shares the query's first two letters and let `score` sort out near misses. A typo in
+    // those two letters, or in a later word of the name, therefore only turns up when Jellyfin
+    // matches the item itself: fetching every item to rank would not scale to large libraries.
+    let mut term_params = item_params.clone();
+    term_params.push(("searchTerm", term.clone()));
+    let mut prefix_params = item_params;
+    prefix_params.push(("NameStartsWith", term.chars().take(2).collect()));
+
+    let items_path = format!("/Users/{}/Items", client.user_id());
+    let hints = client.get_json::<JellyfinSearchHints>("/Search/Hints", &hint_params);
+    let by_term = async {
+        if item_kinds.is_empty() {
+            return Ok(None);
+        }
+        client
+            .get_json::<JellyfinItemsResponse>(&items_path, &term_params)
+            .await
+            .map(Some)
+    };
+    let by_prefix = async {
+        if item_kinds.is_empty() {
+            return Ok(None);
+        }
+        client
+            .get_json::<JellyfinItemsResponse>(&items_path, &prefix_params)
+            .await
+            .map(Some)
+    };
+
+    let (hints, by_term, by_prefix) = futures_util::join!(hints, by_term, by_prefix);
+    let (hints, by_term, by_prefix) = match (hints, by_term, by_prefix) {
+        (Ok(hints), Ok(by_term), Ok(by_prefix)) => (hints, by_term, by_prefix),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => return err.error_response(),
+    };
+
+    // Jellyfin matched these itself, so they stay even when our scoring would drop them.
+    let mut candidates: HashMap<String, (SearchHit, bool)> = HashMap::new();
+    for hit in hints.search_hints.into_iter().map(SearchHit::from) {
+        candidates.insert(hit.id.clone(), (hit, true));
+    }
+    for item in by_term.into_iter().flat_map(|response| response.items) {
+        // Items carry richer artwork than hints, so prefer them.
+        candidates.insert(item.id.clone(), (SearchHit::from(item), true));
+    }
+    for item in by_prefix.into_iter().flat_map(|response| response.items) {
+        candidates
+            .entry(item.id.clone())
+            .or_insert_with(|| (SearchHit::from(item), false));
+    }
+
+    let start_index = query.start_index.unwrap_or(0);
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut groups: HashMap<SearchKind, Vec<SearchHit>> = HashMap::new();
+    for (mut hit, upstream_match) in candidates.into_values() {
+        let Some(kind) = SearchKind::from_jellyfin(&hit.item_type) else {
+            continue;
+        };
+        if !kinds.contains(&kind) {
+            continue;
+        }
+
+        hit.score = match score(&term, &hit.name) {
+            0 if upstream_match => UPSTREAM_MATCH_SCORE,
+            0 => continue,
+            score => score,
+        };
+        groups.entry(kind).or_default().push(hit);
+    }
+
+    let mut page = |kind: SearchKind| {
+        let mut hits = groups.remove(&kind).unwrap_or_default();
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+        SearchGroup {
+            total_record_count: hits.len() as u32,
+            items: hits
+                .into_iter()
+                .skip(start_index as usize)
+                .take(limit as usize)
+                .collect(),
+        }
+    };
+
+    let results = SearchResults {
+        query: term.clone(),
+        start_index,
+        movies: page(SearchKind::Movie),
+        series: page(SearchKind::Series),
+        episodes: page(SearchKind::Episode),
+        people: page(SearchKind::Person),
+    };
+
+    HttpResponse::Ok().json(ApiResponse::ok(results))
+}
+
+/// Ranks `name` against `query`; `0` means no match.
+///
+/// Exact and prefix matches rank highest, then word prefixes and substrings. Otherwise the
+/// query is compared against the same-length prefix of every word, allowing one typo for
+/// queries of four or more characters and two from eight. Only names `search` fetched get
+/// ranked, which limits which typos are found; see the candidate queries there.
+fn score(query: &str, name: &str) -> u32 {
+    let query = normalize(query);
+    let name = normalize(name);
+    if query.is_empty() || name.is_empty() {
+        return 0;
+    }
+
+    if name == query {
+        return 1000;
+    }
+    if name.starts_with(&query) {
+        return 800;
+    }
+
+    let words: Vec<&str> = name.split(' ').collect();
+    if words.iter().any(|word| word.starts_with(&query)) {
+        return 600;
+    }
+    if name.contains(&query) {
+        return 400;
+    }
+
+    let query_len = query.chars().count();
+    let allowed = match query_len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    };
+    if allowed == 0 {
+        return 0;
+    }
+
+    // Compare against every position where a word starts, so multi-word queries still line up.
+    let starts = std::iter::once(0).chain(
+        name.char_indices()
+            .filter(|(_, ch)| *ch == ' ')
+            .map(|(index, _)| index + 1),
+    );
+    let best = starts
+        .map(|start| {
+            let candidate: String = name[start..].chars().take(query_len).collect();
+            edit_distance(&query, &candidate)
+        })
+        .min()
+        .unwrap_or(usize::MAX);
+
+    if best <= allowed {
+        300 - 50 * best as u32
+    } else {
+        0
+    }
+}
+
+fn normalize(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .map(|ch| if ch.is_alphanumeric() { ch } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Levenshtein distance counting adjacent transpositions as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_closer_matches_higher() {
+        let ranked = [
+            score("alien", "Alien"),
+            score("alien", "Aliens"),
+            score("alien", "The Alien Within"),
+            score("lien", "Aliens"),
+            score("alein", "Aliens"),
+            score("aleinn", "Aliens"),
+        ];
+        assert_eq!(ranked, [1000, 800, 600, 400, 250, 0]);
+        assert!(ranked.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+
+    #[test]
+    fn normalizes_case_and_punctuation() {
+        assert_eq!(score("spider man", "Spider-Man"), 1000);
+        assert_eq!(score("  WALL-E ", "WALL·E"), 1000);
+        assert_eq!(score("", "Alien"), 0);
+        assert_eq!(score("?!", "Alien"), 0);
+    }
+
+    #[test]
+    fn allows_more_typos_for_longer_queries() {
+        // Up to three characters only exact substrings count.
+        assert_eq!(score("hte", "The Office"), 0);
+        assert_eq!(score("offcie", "The Office"), 250);
+        assert_eq!(score("offcei", "The Office"), 0);
+        assert_eq!(score("intersteller", "Interstellar"), 250);
+        assert_eq!(score("intersteler", "Interstellar"), 200);
+        assert_eq!(score("intrestelar", "Interstellar"), 0);
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("abc", "abc"), 0);
+        assert_eq!(edit_distance("abc", "abd"), 1);
+        assert_eq!(edit_distance("abc", "acb"), 1);
+        assert_eq!(edit_distance("abc", "abcd"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("été", "ete"), 2);
+    }
+}
@@ -7,6 +7,7 @@ use actix_web::{post, web, HttpResponse, Responder};
 use serde_json::Value;
 use uuid::Uuid;
 
+use crate::jellyfin::Traffic;
 use crate::models::{ApiResponse, SetupRequest};
 use crate::state::AppState;
 
@@ -15,12 +16,13 @@ pub fn init(cfg: &mut web::ServiceConfig) {
 }
 
 fn build_client_header(state: &AppState) -> String {
+    let config = state.config();
     format!(
         "MediaBrowser Client=\"{}\", Device=\"{}\", DeviceId=\"{}\", Version=\"{}\"",
-        state.config.app.client_name,
-        state.config.app.device_name,
+        config.app.client_name,
+        config.app.device_name,
         Uuid::new_v4(),
-        state.config.app.client_version
+        config.app.client_version
     )
 }
 
@@ -33,7 +35,8 @@ async fn validate_server(
     let url = format!("{server_url}/System/Info/Public");
 
     let response = state
-        .http
+        .transport
+        .client(&url, Traffic::Metadata)
         .get(url)
         .header("X-Emby-Authorization", build_client_header(&state))
         .send()
@@ -3,9 +3,10 @@
 //  routes/stream.rs
 //
 
-use actix_web::{get, http::StatusCode, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{get, http::StatusCode, web, HttpRequest, HttpResponse, Responder, ResponseError};
 use futures_util::StreamExt;
 
+use crate::jellyfin::{execute, JellyfinError, Traffic};
 use crate::models::ApiResponse;
 use crate::routes::auth::{build_token_header, load_session, session_id_from_request};
 use crate::state::AppState;
@@ -37,14 +38,19 @@ async fn stream_video(
     };
 
     let item_id = path.into_inner();
+    let Some(ticket) = state.shutdown.stream_started(&session, &item_id) else {
+        return HttpResponse::ServiceUnavailable()
+            .insert_header(("retry-after", "10"))
+            .json(ApiResponse::<()>::err("Server is shutting down"));
+    };
     let server_url = session.server_url.trim_end_matches('/');
     // Use direct stream with mediaSourceId for proper playback
-    let url = format!(
-        "{server_url}/Videos/{item_id}/stream.mp4?static=true&mediaSourceId={item_id}"
-    );
+    let url =
+        format!("{server_url}/Videos/{item_id}/stream.mp4?static=true&mediaSourceId={item_id}");
 
     let mut request = state
-        .http
+        .transport
+        .client(&url, Traffic::Streaming)
         .get(url)
         .header("X-Emby-Authorization", build_token_header(&state, &session));
 
@@ -52,17 +58,22 @@ async fn stream_video(
         request = request.header("range", range);
     }
 
-    let response = match request.send().await {
+    let response = match request.build() {
+        Ok(request) => execute(&state, Traffic::Streaming, request).await,
+        Err(err) => Err(JellyfinError::Request(err)),
+    };
+    let response = match response {
         Ok(res) => res,
-        Err(err) => {
+        Err(JellyfinError::Request(err)) => {
             return HttpResponse::BadGateway().json(ApiResponse::<()>::err(format!(
                 "Streaming request failed: {err}"
             )))
         }
+        Err(err) => return err.error_response(),
     };
 
-    let status = StatusCode::from_u16(response.status().as_u16())
-        .unwrap_or(StatusCode::BAD_GATEWAY);
+    let status =
+        StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
     let content_type = response
         .headers()
         .get("content-type")
@@ -84,8 +95,15 @@ async fn stream_video(
         .and_then(|val| val.to_str().ok())
         .map(|val| val.to_string());
 
-    let stream = response.bytes_stream().map(|chunk| {
-        chunk.map_err(|err| actix_web::error::ErrorBadGateway(err))
+    // The guard and ticket live as long as the body stream, so both are released when the
+    // client leaves.
+    let guard = state.metrics.stream_started();
+    let stream = response.bytes_stream().map(move |chunk| {
+        let _ = &ticket;
+        if let Ok(bytes) = &chunk {
+            guard.add_bytes(bytes.len());
+        }
+        chunk.map_err(actix_web::error::ErrorBadGateway)
     });
 
     let mut builder = HttpResponse::build(status);
@@ -0,0 +1,171 @@
+//
+//  media-savant-api
+//  routes/subtitles.rs
+//
+
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder, ResponseError};
+
+use crate::jellyfin::{JellyfinClient, JellyfinError};
+use crate::models::{
+    ApiResponse, JellyfinMediaSource, JellyfinPlaybackInfo, SubtitleDelivery, SubtitleQuery,
+    SubtitleTrack,
+};
+use crate::routes::auth::require_session;
+use crate::state::AppState;
+use crate::subtitles::TextFormat;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/subtitles")
+            .service(subtitle_vtt)
+            .service(list_subtitles),
+    );
+}
+
+#[get("/{item_id}")]
+async fn list_subtitles(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<SubtitleQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let item_id = path.into_inner();
+    let source = match media_source(&client, &item_id, query.media_source_id.as_deref()).await {
+        Ok(source) => source,
+        Err(response) => return response,
+    };
+
+    let tracks: Vec<SubtitleTrack> = source
+        .media_streams
+        .iter()
+        .filter(|stream| stream.stream_type == "Subtitle")
+        .map(|stream| {
+            let codec = stream.codec.clone().unwrap_or_default().to_ascii_lowercase();
+            let (delivery, url) = match TextFormat::from_codec(&codec) {
+                Some(_) => (
+                    SubtitleDelivery::Vtt,
+                    format!(
+                        "/api/subtitles/{item_id}/{}.vtt?media_source_id={}",
+                        stream.index, source.id
+                    ),
+                ),
+                None => (
+                    SubtitleDelivery::Encode,
+                    format!(
+                        "/api/jellyfin/Videos/{item_id}/master.m3u8?MediaSourceId={}&SubtitleStreamIndex={}&SubtitleMethod=Encode&VideoCodec=h264&AudioCodec=aac",
+                        source.id, stream.index
+                    ),
+                ),
+            };
+
+            SubtitleTrack {
+                index: stream.index,
+                codec,
+                language: stream.language.clone(),
+                title: stream.display_title.clone().or_else(|| stream.title.clone()),
+                is_default: stream.is_default,
+                is_forced: stream.is_forced,
+                is_external: stream.is_external,
+                delivery,
+                url,
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(ApiResponse::ok(tracks))
+}
+
+#[get("/{item_id}/{index}.vtt")]
+async fn subtitle_vtt(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, i32)>,
+    query: web::Query<SubtitleQuery>,
+) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    let (item_id, index) = path.into_inner();
+    let source = match media_source(&client, &item_id, query.media_source_id.as_deref()).await {
+        Ok(source) => source,
+        Err(response) => return response,
+    };
+
+    let Some(stream) = source
+        .media_streams
+        .iter()
+        .find(|stream| stream.index == index && stream.stream_type == "Subtitle")
+    else {
+        return HttpResponse::NotFound().json(ApiResponse::<()>::err("Subtitle stream not found"));
+    };
+
+    let codec = stream.codec.clone().unwrap_or_default();
+    let Some(format) = TextFormat::from_codec(&codec) else {
+        return HttpResponse::UnsupportedMediaType().json(ApiResponse::<()>::err(format!(
+            "Subtitle codec {codec} is image based and must be burned in"
+        )));
+    };
+
+    let path = format!(
+        "/Videos/{item_id}/{}/Subtitles/{index}/Stream.{}",
+        source.id,
+        format.extension()
+    );
+    let raw = match client.get_text(&path, &[]).await {
+        Ok(raw) => raw,
+        Err(err) => return err.error_response(),
+    };
+
+    let offset_ms = query.offset_ms;
+    let vtt = match web::block(move || format.to_vtt(&raw, offset_ms)).await {
+        Ok(vtt) => vtt,
+        Err(err) => {
+            return HttpResponse::InternalServerError().json(ApiResponse::<()>::err(format!(
+                "Subtitle conversion failed: {err}"
+            )))
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/vtt; charset=utf-8")
+        .body(vtt)
+}
+
+async fn media_source(
+    client: &JellyfinClient<'_>,
+    item_id: &str,
+    media_source_id: Option<&str>,
+) -> Result<JellyfinMediaSource, HttpResponse> {
+    let info = client
+        .get_json::<JellyfinPlaybackInfo>(
+            &format!("/Items/{item_id}/PlaybackInfo"),
+            &[("UserId", client.user_id().to_string())],
+        )
+        .await
+        .map_err(|err: JellyfinError| err.error_response())?;
+
+    let mut sources = info.media_sources;
+    let position = match media_source_id {
+        Some(id) => sources.iter().position(|source| source.id == id),
+        // Without an explicit source prefer the one matching the item, as `/stream` does.
+        None => sources
+            .iter()
+            .position(|source| source.id == item_id)
+            .or((!sources.is_empty()).then_some(0)),
+    };
+
+    position
+        .map(|position| sources.swap_remove(position))
+        .ok_or_else(|| {
+            HttpResponse::NotFound().json(ApiResponse::<()>::err("Media source not found"))
+        })
+}
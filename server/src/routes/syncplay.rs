@@ -0,0 +1,222 @@
+//
+//  media-savant-api
+//  routes/syncplay.rs
+//
+
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder, ResponseError};
+use serde_json::{json, Value};
+
+use crate::jellyfin::JellyfinClient;
+use crate::models::{
+    ApiResponse, CreateGroupRequest, GroupQueueMode, GroupQueueRequest, JellyfinSyncPlayGroup,
+    PlaylistItemRequest, ReadyStateRequest, SeekRequest, SyncPlayGroup, SyncPlayPingRequest,
+};
+use crate::routes::auth::require_session;
+use crate::state::AppState;
+
+/// SyncPlay membership belongs to the Jellyfin session of this device, and group updates reach
+/// the browser through `/api/events` (`SyncPlayGroupUpdate` and `SyncPlayCommand`).
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/syncplay")
+            .service(list_groups)
+            .service(create_group)
+            .service(join_group)
+            .service(leave_group)
+            .service(pause)
+            .service(unpause)
+            .service(stop)
+            .service(seek)
+            .service(set_queue)
+            .service(next_item)
+            .service(previous_item)
+            .service(ready)
+            .service(buffering)
+            .service(ping),
+    );
+}
+
+#[get("/groups")]
+async fn list_groups(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let session = match require_session(&state, &req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(&state, &session);
+
+    match client
+        .get_json::<Vec<JellyfinSyncPlayGroup>>("/SyncPlay/List", &[])
+        .await
+    {
+        Ok(groups) => {
+            let groups: Vec<SyncPlayGroup> = groups.into_iter().map(SyncPlayGroup::from).collect();
+            HttpResponse::Ok().json(ApiResponse::ok(groups))
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[post("/groups")]
+async fn create_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreateGroupRequest>,
+) -> impl Responder {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("Group name is empty"));
+    }
+    group_command(&state, &req, "New", Some(json!({ "GroupName": name }))).await
+}
+
+#[post("/groups/{group_id}/join")]
+async fn join_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let body = json!({ "GroupId": path.into_inner() });
+    group_command(&state, &req, "Join", Some(body)).await
+}
+
+#[post("/leave")]
+async fn leave_group(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    group_command(&state, &req, "Leave", None).await
+}
+
+#[post("/pause")]
+async fn pause(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    group_command(&state, &req, "Pause", None).await
+}
+
+#[post("/unpause")]
+async fn unpause(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    group_command(&state, &req, "Unpause", None).await
+}
+
+#[post("/stop")]
+async fn stop(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    group_command(&state, &req, "Stop", None).await
+}
+
+#[post("/seek")]
+async fn seek(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<SeekRequest>,
+) -> impl Responder {
+    let body = json!({ "PositionTicks": body.position_ticks.max(0) });
+    group_command(&state, &req, "Seek", Some(body)).await
+}
+
+#[post("/queue")]
+async fn set_queue(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<GroupQueueRequest>,
+) -> impl Responder {
+    if body.item_ids.is_empty() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::err("item_ids is empty"));
+    }
+
+    let (command, payload) = match body.mode {
+        GroupQueueMode::Replace => (
+            "SetNewQueue",
+            json!({
+                "PlayingQueue": body.item_ids,
+                "PlayingItemPosition": body.start_index.unwrap_or(0),
+                "StartPositionTicks": body.start_position_ticks.unwrap_or(0).max(0),
+            }),
+        ),
+        GroupQueueMode::Next => (
+            "Queue",
+            json!({ "ItemIds": body.item_ids, "Mode": "QueueNext" }),
+        ),
+        GroupQueueMode::Last => (
+            "Queue",
+            json!({ "ItemIds": body.item_ids, "Mode": "Queue" }),
+        ),
+    };
+    group_command(&state, &req, command, Some(payload)).await
+}
+
+#[post("/next")]
+async fn next_item(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<PlaylistItemRequest>,
+) -> impl Responder {
+    let body = json!({ "PlaylistItemId": body.playlist_item_id });
+    group_command(&state, &req, "NextItem", Some(body)).await
+}
+
+#[post("/previous")]
+async fn previous_item(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<PlaylistItemRequest>,
+) -> impl Responder {
+    let body = json!({ "PlaylistItemId": body.playlist_item_id });
+    group_command(&state, &req, "PreviousItem", Some(body)).await
+}
+
+#[post("/ready")]
+async fn ready(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<ReadyStateRequest>,
+) -> impl Responder {
+    group_command(&state, &req, "Ready", Some(ready_state(&body))).await
+}
+
+#[post("/buffering")]
+async fn buffering(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<ReadyStateRequest>,
+) -> impl Responder {
+    group_command(&state, &req, "Buffering", Some(ready_state(&body))).await
+}
+
+#[post("/ping")]
+async fn ping(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<SyncPlayPingRequest>,
+) -> impl Responder {
+    let body = json!({ "Ping": body.ping_ms });
+    group_command(&state, &req, "Ping", Some(body)).await
+}
+
+fn ready_state(body: &ReadyStateRequest) -> Value {
+    json!({
+        "When": body.when,
+        "PositionTicks": body.position_ticks.max(0),
+        "IsPlaying": body.is_playing,
+        "PlaylistItemId": body.playlist_item_id,
+    })
+}
+
+/// Sends a SyncPlay request as this device's Jellyfin session.
+async fn group_command(
+    state: &AppState,
+    req: &HttpRequest,
+    command: &str,
+    body: Option<Value>,
+) -> HttpResponse {
+    let session = match require_session(state, req).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let client = JellyfinClient::new(state, &session);
+
+    let path = format!("/SyncPlay/{command}");
+    let result = match body {
+        Some(body) => client.post_json(&path, &[], &body).await,
+        None => client.post(&path, &[]).await,
+    };
+    match result {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::ok(json!({ "sent": true }))),
+        Err(err) => err.error_response(),
+    }
+}
@@ -0,0 +1,220 @@
+//
+//  media-savant-api
+//  shutdown/mod.rs
+//
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::dev::ServerHandle;
+use actix_web::web;
+use futures_util::future::join_all;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Notify;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+use crate::jellyfin::JellyfinClient;
+use crate::models::{JellyfinPlaybackProgressInfo, JellyfinPlaybackStopInfo, SessionData};
+use crate::state::AppState;
+
+/// Upper bound for reporting every cut stream to Jellyfin as stopped.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long requests other than streams may run on once streams have drained.
+pub const REQUEST_DRAIN_SECS: u64 = 5;
+
+/// Tracks running video streams so a stop signal can wait for them, and refuses new ones once
+/// shutdown has begun.
+#[derive(Default)]
+pub struct Shutdown {
+    draining: AtomicBool,
+    next_id: AtomicU64,
+    streams: Mutex<HashMap<u64, ActiveStream>>,
+    idle: Notify,
+}
+
+struct ActiveStream {
+    session: SessionData,
+    item_id: String,
+    /// The client's last progress report for the item, if it sent one through the proxy.
+    progress: Option<JellyfinPlaybackProgressInfo>,
+}
+
+/// Held for the lifetime of a relayed stream; dropping it unregisters the stream.
+pub struct StreamTicket {
+    shutdown: Arc<Shutdown>,
+    id: u64,
+}
+
+impl Shutdown {
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Registers a stream until the returned ticket is dropped; `None` once draining.
+    pub fn stream_started(
+        self: &Arc<Self>,
+        session: &SessionData,
+        item_id: &str,
+    ) -> Option<StreamTicket> {
+        let mut streams = self.streams.lock().unwrap();
+        if self.is_draining() {
+            return None;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        streams.insert(
+            id,
+            ActiveStream {
+                session: session.clone(),
+                item_id: item_id.to_string(),
+                progress: None,
+            },
+        );
+        Some(StreamTicket {
+            shutdown: self.clone(),
+            id,
+        })
+    }
+
+    /// Remembers where a client is in a video it is streaming, for the stop report should
+    /// shutdown cut it.
+    pub fn record_progress(&self, session: &SessionData, progress: JellyfinPlaybackProgressInfo) {
+        let mut streams = self.streams.lock().unwrap();
+        for stream in streams.values_mut().filter(|stream| {
+            stream.session.session_id == session.session_id && stream.item_id == progress.item_id
+        }) {
+            stream.progress = Some(progress.clone());
+        }
+    }
+
+    /// Stops admitting streams and returns how many are still running.
+    fn begin(&self) -> usize {
+        let streams = self.streams.lock().unwrap();
+        self.draining.store(true, Ordering::Relaxed);
+        streams.len()
+    }
+
+    /// Whether every stream finished within `grace`.
+    async fn wait_for_streams(&self, grace: Duration) -> bool {
+        let idle = async {
+            while !self.streams.lock().unwrap().is_empty() {
+                self.idle.notified().await;
+            }
+        };
+        timeout(grace, idle).await.is_ok()
+    }
+
+    /// Streams still running, one per session and item, as range requests for the same video
+    /// are reported to Jellyfin once. Streams without an item, such as live TV, have nothing to
+    /// report.
+    fn remaining(&self) -> Vec<(SessionData, JellyfinPlaybackStopInfo)> {
+        let streams = self.streams.lock().unwrap();
+        let mut seen = HashSet::new();
+        streams
+            .values()
+            .filter(|stream| !stream.item_id.is_empty())
+            .filter(|stream| seen.insert((stream.session.session_id, stream.item_id.clone())))
+            .map(|stream| (stream.session.clone(), stream.stop_info()))
+            .collect()
+    }
+}
+
+impl ActiveStream {
+    /// Reports the last known position. A client that never reported progress leaves no
+    /// position to keep, so the stop is marked failed rather than letting Jellyfin count the
+    /// item as watched; its resume point stays wherever it was before.
+    fn stop_info(&self) -> JellyfinPlaybackStopInfo {
+        let progress = self.progress.as_ref();
+        let position_ticks = progress.and_then(|progress| progress.position_ticks);
+        JellyfinPlaybackStopInfo {
+            item_id: self.item_id.clone(),
+            media_source_id: progress
+                .and_then(|progress| progress.media_source_id.clone())
+                .unwrap_or_else(|| self.item_id.clone()),
+            position_ticks,
+            play_session_id: progress.and_then(|progress| progress.play_session_id.clone()),
+            failed: position_ticks.is_none(),
+        }
+    }
+}
+
+impl Drop for StreamTicket {
+    fn drop(&mut self) {
+        let mut streams = self.shutdown.streams.lock().unwrap();
+        streams.remove(&self.id);
+        if streams.is_empty() {
+            self.shutdown.idle.notify_one();
+        }
+    }
+}
+
+/// Waits for `SIGTERM` or Ctrl-C, then stops the server without cutting streams short.
+///
+/// New streams are refused and readiness reports `down` straight away, while other requests
+/// (including `/metrics`) are still served. Running streams get the configured grace period;
+/// any left after it are reported to Jellyfin as stopped and the server stops at once. When they
+/// all finish in time, other requests get `REQUEST_DRAIN_SECS` to complete. A second signal
+/// skips the wait.
+pub async fn on_signal(state: web::Data<AppState>, server: ServerHandle) {
+    wait_for_signal().await;
+
+    let grace = state.config().shutdown.grace_period;
+    let active = state.shutdown.begin();
+    info!(
+        active_streams = active,
+        "Shutting down, waiting up to {}s for streams to finish",
+        grace.as_secs()
+    );
+
+    let drained = tokio::select! {
+        drained = state.shutdown.wait_for_streams(grace) => drained,
+        _ = wait_for_signal() => false,
+    };
+    if !drained {
+        let remaining = state.shutdown.remaining();
+        warn!(
+            "Cutting {} stream(s) still running after the grace period",
+            remaining.len()
+        );
+        report_stopped(&state, &remaining).await;
+    }
+
+    server.stop(drained).await;
+}
+
+async fn wait_for_signal() {
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+        Err(err) => {
+            warn!("Cannot listen for SIGTERM: {err}");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+/// Tells Jellyfin playback ended, so the sessions don't linger as "now playing".
+async fn report_stopped(state: &AppState, streams: &[(SessionData, JellyfinPlaybackStopInfo)]) {
+    let reports = streams.iter().map(|(session, stop)| async move {
+        let result = JellyfinClient::new(state, session)
+            .post_json("/Sessions/Playing/Stopped", &[], stop)
+            .await;
+        if let Err(err) = result {
+            warn!(
+                user_id = %session.user_id,
+                item_id = %stop.item_id,
+                "Failed to report playback stopped: {err}"
+            );
+        }
+    });
+
+    if timeout(REPORT_TIMEOUT, join_all(reports)).await.is_err() {
+        warn!("Gave up reporting playback stopped to Jellyfin");
+    }
+}
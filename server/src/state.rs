@@ -3,30 +3,74 @@
 //  state.rs
 //
 
-use crate::config::Config;
+use crate::config::{Config, ReloadStatus};
+use crate::events::EventHub;
+use crate::images::ImageCache;
+use crate::jellyfin::{CircuitBreakers, MetadataCache, Transport};
+use crate::metrics::Metrics;
+use crate::models::QueueLocks;
+use crate::ratelimit::RateLimiter;
+use crate::shutdown::Shutdown;
+use crate::tls::CertResolver;
+use arc_swap::ArcSwap;
 use redis::aio::MultiplexedConnection;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex as TokioMutex;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub config: Arc<Config>,
+    /// Swapped as a whole on reload; read it through `config()`.
+    pub config: Arc<ArcSwap<Config>>,
     pub redis: Arc<TokioMutex<MultiplexedConnection>>,
-    pub http: reqwest::Client,
+    pub transport: Arc<Transport>,
+    pub breakers: Arc<CircuitBreakers>,
+    pub images: Arc<ImageCache>,
+    pub metadata: Arc<MetadataCache>,
+    pub queue_locks: Arc<QueueLocks>,
+    pub events: Arc<EventHub>,
+    pub metrics: Arc<Metrics>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub reload: Arc<ReloadStatus>,
+    pub shutdown: Arc<Shutdown>,
+    /// Present when the API terminates TLS itself.
+    pub certs: Option<Arc<CertResolver>>,
+    pub started_at: Instant,
 }
 
 impl AppState {
     pub async fn new(config: Config) -> Result<Self, Box<dyn std::error::Error>> {
-        let redis_url = config.redis.url.clone();
+        let redis_url = config.redis.url.expose().clone();
         let redis_client = redis::Client::open(redis_url)?;
         let redis_conn = redis_client.get_multiplexed_async_connection().await?;
 
-        let http = reqwest::Client::builder().build()?;
+        let transport = Transport::new(&config)?;
+        let images = ImageCache::open(&config.images.dir, config.images.max_bytes)?;
+        let metadata = MetadataCache::new(config.metadata_cache.clone());
+        let rate_limiter = RateLimiter::new(&config.rate_limit);
+        let certs = config.tls.as_ref().map(CertResolver::new).transpose()?;
 
         Ok(Self {
-            config: Arc::new(config),
+            config: Arc::new(ArcSwap::from_pointee(config)),
             redis: Arc::new(TokioMutex::new(redis_conn)),
-            http,
+            transport: Arc::new(transport),
+            breakers: Arc::new(CircuitBreakers::default()),
+            images: Arc::new(images),
+            metadata: Arc::new(metadata),
+            queue_locks: Arc::new(QueueLocks::default()),
+            events: Arc::new(EventHub::default()),
+            metrics: Arc::new(Metrics::new()?),
+            rate_limiter: Arc::new(rate_limiter),
+            reload: Arc::new(ReloadStatus::default()),
+            shutdown: Arc::new(Shutdown::default()),
+            certs: certs.map(Arc::new),
+            started_at: Instant::now(),
         })
     }
+
+    /// The configuration currently in effect. Hold on to the returned value for the length of
+    /// one request so its settings stay consistent across a concurrent reload.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.load_full()
+    }
 }
@@ -0,0 +1,566 @@
+//
+//  media-savant-api
+//  subtitles/mod.rs
+//
+
+use std::collections::HashMap;
+
+/// Text subtitle codecs that can be converted to WebVTT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    Srt,
+    Ass,
+    Ssa,
+    Vtt,
+}
+
+impl TextFormat {
+    /// Maps a Jellyfin stream codec; image based codecs (PGS, VobSub, DVB) yield `None`.
+    pub fn from_codec(codec: &str) -> Option<Self> {
+        match codec.to_ascii_lowercase().as_str() {
+            "srt" | "subrip" => Some(Self::Srt),
+            "ass" => Some(Self::Ass),
+            "ssa" => Some(Self::Ssa),
+            "vtt" | "webvtt" => Some(Self::Vtt),
+            _ => None,
+        }
+    }
+
+    /// Extension Jellyfin uses to deliver the stream unconverted.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::Ass => "ass",
+            Self::Ssa => "ssa",
+            Self::Vtt => "vtt",
+        }
+    }
+
+    pub fn to_vtt(self, input: &str, offset_ms: i64) -> String {
+        match self {
+            Self::Srt => srt_to_vtt(input, offset_ms),
+            Self::Ass | Self::Ssa => ass_to_vtt(input, offset_ms),
+            Self::Vtt => shift_vtt(input, offset_ms),
+        }
+    }
+}
+
+/// A single timed cue, already reduced to WebVTT markup.
+#[derive(Debug, Clone)]
+struct Cue {
+    start_ms: i64,
+    end_ms: i64,
+    settings: String,
+    text: String,
+}
+
+/// Converts SubRip (`.srt`) subtitles to WebVTT, shifting every cue by `offset_ms`.
+pub fn srt_to_vtt(input: &str, offset_ms: i64) -> String {
+    let cues = parse_timed_blocks(input)
+        .into_iter()
+        .map(|(start_ms, end_ms, _, lines)| Cue {
+            start_ms,
+            end_ms,
+            settings: String::new(),
+            text: lines
+                .iter()
+                .map(|line| convert_ass_text(&sanitize_markup(line)).0)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+        .collect();
+
+    render_vtt(cues, offset_ms)
+}
+
+/// Re-emits WebVTT subtitles with every cue shifted by `offset_ms`.
+pub fn shift_vtt(input: &str, offset_ms: i64) -> String {
+    let cues = parse_timed_blocks(input)
+        .into_iter()
+        .map(|(start_ms, end_ms, settings, lines)| Cue {
+            start_ms,
+            end_ms,
+            settings,
+            text: lines.join("\n"),
+        })
+        .collect();
+
+    render_vtt(cues, offset_ms)
+}
+
+/// Converts ASS/SSA subtitles to WebVTT.
+///
+/// Only styling WebVTT can express survives: italic, bold and underline overrides, and the
+/// numpad alignment mapped onto `line`/`align` cue settings. Fonts, colours, karaoke and
+/// drawing commands are dropped.
+pub fn ass_to_vtt(input: &str, offset_ms: i64) -> String {
+    let mut section = String::new();
+    let mut style_format: Vec<String> = Vec::new();
+    let mut event_format: Vec<String> = Vec::new();
+    let mut style_alignment: HashMap<String, u8> = HashMap::new();
+    let mut cues = Vec::new();
+
+    for line in normalize(input).lines() {
+        let line = line.trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line.to_ascii_lowercase();
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim_start();
+
+        match (section.as_str(), key.as_str()) {
+            ("[v4 styles]" | "[v4+ styles]", "format") => style_format = parse_format(value),
+            ("[v4 styles]" | "[v4+ styles]", "style") => {
+                let fields = split_fields(value, style_format.len());
+                let (Some(name), Some(alignment)) = (
+                    field(&style_format, &fields, "name"),
+                    field(&style_format, &fields, "alignment").and_then(|v| v.parse::<u8>().ok()),
+                ) else {
+                    continue;
+                };
+                let alignment = if section == "[v4 styles]" {
+                    legacy_alignment(alignment)
+                } else {
+                    alignment
+                };
+                style_alignment.insert(name.to_string(), alignment);
+            }
+            ("[events]", "format") => event_format = parse_format(value),
+            ("[events]", "dialogue") => {
+                if event_format.is_empty() {
+                    event_format = parse_format(
+                        "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text",
+                    );
+                }
+                let fields = split_fields(value, event_format.len());
+                let (Some(start_ms), Some(end_ms), Some(raw_text)) = (
+                    field(&event_format, &fields, "start").and_then(parse_timestamp),
+                    field(&event_format, &fields, "end").and_then(parse_timestamp),
+                    field(&event_format, &fields, "text"),
+                ) else {
+                    continue;
+                };
+
+                // ASS has no markup besides override blocks, so angle brackets are literal.
+                let escaped = raw_text
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;");
+                let (text, inline_alignment) = convert_ass_text(&escaped);
+                if text.trim().is_empty() {
+                    continue;
+                }
+
+                let alignment = inline_alignment.or_else(|| {
+                    field(&event_format, &fields, "style")
+                        .and_then(|style| style_alignment.get(style.trim_start_matches('*')))
+                        .copied()
+                });
+
+                cues.push(Cue {
+                    start_ms,
+                    end_ms,
+                    settings: alignment.map(alignment_settings).unwrap_or_default(),
+                    text,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    cues.sort_by_key(|cue| cue.start_ms);
+    render_vtt(cues, offset_ms)
+}
+
+fn normalize(input: &str) -> String {
+    input
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+/// Parses SRT/WebVTT style blocks into `(start, end, cue settings, text lines)`.
+fn parse_timed_blocks(input: &str) -> Vec<(i64, i64, String, Vec<String>)> {
+    let input = normalize(input);
+    let mut blocks = Vec::new();
+    let mut lines = input.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let Some((start, rest)) = line.split_once("-->") else {
+            continue;
+        };
+        let rest = rest.trim();
+        let (end, settings) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let (Some(start_ms), Some(end_ms)) = (parse_timestamp(start), parse_timestamp(end)) else {
+            continue;
+        };
+
+        let mut text = Vec::new();
+        while let Some(next) = lines.peek() {
+            if next.trim().is_empty() {
+                break;
+            }
+            text.push(next.trim_end().to_string());
+            lines.next();
+        }
+
+        // SRT coordinates (`X1:... Y2:...`) have no WebVTT equivalent.
+        let settings = if settings.contains("X1:") {
+            String::new()
+        } else {
+            settings.trim().to_string()
+        };
+        blocks.push((start_ms, end_ms, settings, text));
+    }
+
+    blocks
+}
+
+/// Parses `HH:MM:SS,mmm`, `MM:SS.mmm` and ASS `H:MM:SS.cc` timestamps into milliseconds.
+fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let parts: Vec<&str> = value.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [h, m, s] => (h.parse::<i64>().ok()?, m.parse::<i64>().ok()?, *s),
+        [m, s] => (0, m.parse::<i64>().ok()?, *s),
+        _ => return None,
+    };
+
+    let (whole, fraction) = seconds.split_once([',', '.']).unwrap_or((seconds, "0"));
+    let whole = whole.parse::<i64>().ok()?;
+    if !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let fraction_ms = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<i64>().ok()? * 100,
+        2 => fraction.parse::<i64>().ok()? * 10,
+        _ => fraction[..3].parse::<i64>().ok()?,
+    };
+
+    // Checked, as the hours field can hold any number a file cares to put there.
+    let seconds = hours
+        .checked_mul(60)?
+        .checked_add(minutes)?
+        .checked_mul(60)?
+        .checked_add(whole)?;
+    seconds.checked_mul(1000)?.checked_add(fraction_ms)
+}
+
+fn format_timestamp(ms: i64) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1000) % 60,
+        ms % 1000
+    )
+}
+
+fn render_vtt(cues: Vec<Cue>, offset_ms: i64) -> String {
+    let mut output = String::from("WEBVTT\n\n");
+
+    for cue in cues {
+        // `offset_ms` comes straight from the query string.
+        let start_ms = cue.start_ms.saturating_add(offset_ms).max(0);
+        let end_ms = cue.end_ms.saturating_add(offset_ms);
+        if end_ms <= start_ms {
+            continue;
+        }
+
+        output.push_str(&format_timestamp(start_ms));
+        output.push_str(" --> ");
+        output.push_str(&format_timestamp(end_ms));
+        if !cue.settings.is_empty() {
+            output.push(' ');
+            output.push_str(&cue.settings);
+        }
+        output.push('\n');
+        // A blank line would terminate the cue early.
+        for line in cue.text.lines().filter(|line| !line.trim().is_empty()) {
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+fn parse_format(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect()
+}
+
+/// Splits a comma separated ASS line; the last field (the text) may itself contain commas.
+fn split_fields(value: &str, count: usize) -> Vec<&str> {
+    value.splitn(count.max(1), ',').map(str::trim).collect()
+}
+
+fn field<'a>(format: &[String], fields: &[&'a str], name: &str) -> Option<&'a str> {
+    format
+        .iter()
+        .position(|column| column == name)
+        .and_then(|index| fields.get(index).copied())
+}
+
+/// Maps SSA v4 alignment (1-3 bottom, +4 top, +8 middle) onto the ASS numpad layout.
+fn legacy_alignment(value: u8) -> u8 {
+    match value {
+        5..=7 => value + 2,
+        9..=11 => value - 5,
+        other => other,
+    }
+}
+
+fn alignment_settings(alignment: u8) -> String {
+    let line = match alignment {
+        7..=9 => Some("line:0"),
+        4..=6 => Some("line:50%"),
+        _ => None,
+    };
+    let align = match alignment {
+        1 | 4 | 7 => Some("align:start"),
+        3 | 6 | 9 => Some("align:end"),
+        _ => None,
+    };
+
+    [line, align]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Rewrites ASS override blocks (`{\i1}`, `{\an8}`, ...) into WebVTT tags, returning the text
+/// and any inline alignment override.
+fn convert_ass_text(raw: &str) -> (String, Option<u8>) {
+    let mut output = String::new();
+    let mut alignment = None;
+    let mut open: Vec<char> = Vec::new();
+    let mut drawing = false;
+    let mut chars = raw.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' => {
+                let mut block = String::new();
+                for next in chars.by_ref() {
+                    if next == '}' {
+                        break;
+                    }
+                    block.push(next);
+                }
+
+                for tag in block
+                    .split('\\')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                {
+                    if let Some(value) = tag.strip_prefix("an") {
+                        alignment = value.parse::<u8>().ok().or(alignment);
+                    } else if let Some(value) =
+                        tag.strip_prefix('a').filter(|v| v.parse::<u8>().is_ok())
+                    {
+                        alignment = value.parse::<u8>().ok().map(legacy_alignment).or(alignment);
+                    } else if let Some(value) =
+                        tag.strip_prefix('p').filter(|v| v.parse::<u8>().is_ok())
+                    {
+                        drawing = value != "0";
+                    } else if tag == "r" {
+                        while let Some(tag) = open.pop() {
+                            output.push_str(&format!("</{tag}>"));
+                        }
+                    } else {
+                        let mut name = tag.chars();
+                        let (Some(kind @ ('i' | 'b' | 'u')), value) = (name.next(), name.as_str())
+                        else {
+                            continue;
+                        };
+                        let Ok(value) = value.parse::<u32>() else {
+                            continue;
+                        };
+                        toggle_tag(&mut output, &mut open, kind, value != 0);
+                    }
+                }
+            }
+            '\\' if !drawing => match chars.peek() {
+                Some('N') | Some('n') => {
+                    chars.next();
+                    output.push('\n');
+                }
+                Some('h') => {
+                    chars.next();
+                    output.push('\u{a0}');
+                }
+                _ => output.push(ch),
+            },
+            _ if drawing => {}
+            _ => output.push(ch),
+        }
+    }
+
+    while let Some(tag) = open.pop() {
+        output.push_str(&format!("</{tag}>"));
+    }
+
+    (output, alignment)
+}
+
+fn toggle_tag(output: &mut String, open: &mut Vec<char>, kind: char, enable: bool) {
+    let is_open = open.contains(&kind);
+    if enable && !is_open {
+        open.push(kind);
+        output.push_str(&format!("<{kind}>"));
+    } else if !enable && is_open {
+        // WebVTT requires proper nesting, so close and reopen anything opened after `kind`.
+        let mut reopen = Vec::new();
+        while let Some(tag) = open.pop() {
+            output.push_str(&format!("</{tag}>"));
+            if tag == kind {
+                break;
+            }
+            reopen.push(tag);
+        }
+        for tag in reopen.into_iter().rev() {
+            open.push(tag);
+            output.push_str(&format!("<{tag}>"));
+        }
+    }
+}
+
+/// Keeps only the `<b>`, `<i>` and `<u>` tags WebVTT understands and escapes everything else.
+fn sanitize_markup(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(index) = rest.find(['<', '&', '>']) {
+        output.push_str(&rest[..index]);
+        let tail = &rest[index..];
+
+        if let Some(after) = tail.strip_prefix('&') {
+            output.push_str("&amp;");
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix('>') {
+            output.push_str("&gt;");
+            rest = after;
+        } else if let Some(close) = tail.find('>') {
+            let tag = tail[1..close].trim().to_ascii_lowercase();
+            let name = tag
+                .trim_start_matches('/')
+                .split_whitespace()
+                .next()
+                .unwrap_or("");
+            if matches!(name, "b" | "i" | "u") {
+                let slash = if tag.starts_with('/') { "/" } else { "" };
+                output.push_str(&format!("<{slash}{name}>"));
+            }
+            rest = &tail[close + 1..];
+        } else {
+            output.push_str("&lt;");
+            rest = &tail[1..];
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_srt() {
+        let input = "\u{feff}1\r\n00:00:01,500 --> 00:00:03,000\r\n<i>Hello</i> & <font color=\"red\">world</font>\r\nSecond line\r\n\r\n2\r\n00:01:00,000 --> 00:01:02,250 X1:10 X2:20 Y1:30 Y2:40\r\n{\\an8}Top\r\n";
+        assert_eq!(
+            srt_to_vtt(input, 0),
+            "WEBVTT\n\n\
+             00:00:01.500 --> 00:00:03.000\n<i>Hello</i> &amp; world\nSecond line\n\n\
+             00:01:00.000 --> 00:01:02.250\nTop\n\n"
+        );
+    }
+
+    #[test]
+    fn converts_ass_overrides() {
+        let input = "[Script Info]\n\
+             Title: Test\n\n\
+             [V4+ Styles]\n\
+             Format: Name, Fontname, Alignment\n\
+             Style: Sign,Arial,8\n\n\
+             [Events]\n\
+             Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+             Dialogue: 0,0:00:05.00,0:00:06.50,Default,,0,0,0,,{\\i1}Half{\\i0}, then <b>\\Nnext\n\
+             Dialogue: 0,0:00:01.00,0:00:02.00,Sign,,0,0,0,,Up top\n\
+             Dialogue: 0,0:00:03.00,0:00:04.00,Default,,0,0,0,,{\\an1\\b1\\u1}Both{\\b0} on\n\
+             Dialogue: 0,0:00:07.00,0:00:08.00,Default,,0,0,0,,{\\p1}m 0 0 l 10 10{\\p0}\n";
+        assert_eq!(
+            ass_to_vtt(input, 0),
+            "WEBVTT\n\n\
+             00:00:01.000 --> 00:00:02.000 line:0\nUp top\n\n\
+             00:00:03.000 --> 00:00:04.000 align:start\n<b><u>Both</u></b><u> on</u>\n\n\
+             00:00:05.000 --> 00:00:06.500\n<i>Half</i>, then &lt;b&gt;\nnext\n\n"
+        );
+    }
+
+    #[test]
+    fn applies_offsets() {
+        let input = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000 align:end\nFirst\n\n\
+                     00:00:05.000 --> 00:00:06.000\nSecond\n";
+        assert_eq!(
+            shift_vtt(input, 1500),
+            "WEBVTT\n\n\
+             00:00:02.500 --> 00:00:03.500 align:end\nFirst\n\n\
+             00:00:06.500 --> 00:00:07.500\nSecond\n\n"
+        );
+        // Cues shifted before zero are clamped, or dropped once they end before it.
+        assert_eq!(
+            shift_vtt(input, -1500),
+            "WEBVTT\n\n\
+             00:00:00.000 --> 00:00:00.500 align:end\nFirst\n\n\
+             00:00:03.500 --> 00:00:04.500\nSecond\n\n"
+        );
+        assert_eq!(
+            shift_vtt(input, -2000),
+            "WEBVTT\n\n00:00:03.000 --> 00:00:04.000\nSecond\n\n"
+        );
+        // Extreme offsets saturate instead of overflowing, collapsing every cue.
+        assert_eq!(shift_vtt(input, i64::MIN), "WEBVTT\n\n");
+        assert_eq!(shift_vtt(input, i64::MAX), "WEBVTT\n\n");
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("01:02:03,456"), Some(3_723_456));
+        assert_eq!(parse_timestamp("02:03.4"), Some(123_400));
+        assert_eq!(parse_timestamp("0:00:01.25"), Some(1_250));
+        assert_eq!(parse_timestamp("00:00:01,23456"), Some(1_234));
+        assert_eq!(parse_timestamp("00:00:01"), Some(1_000));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        assert_eq!(parse_timestamp("00:00:01,1\u{20ac}"), None);
+        assert_eq!(parse_timestamp("00:00:01,\u{20ac}\u{20ac}"), None);
+        assert_eq!(parse_timestamp("00:00:01,+5"), None);
+        assert_eq!(parse_timestamp("aa:00:01,000"), None);
+        assert_eq!(parse_timestamp("1:2:3:4"), None);
+        assert_eq!(parse_timestamp("9223372036854775807:00:00.000"), None);
+        assert_eq!(parse_timestamp(""), None);
+
+        let input = "1\n00:00:01,1\u{20ac} --> 00:00:02,000\nBroken\n\n\
+                     2\n00:00:03,000 --> 00:00:04,000\nKept\n";
+        assert_eq!(
+            srt_to_vtt(input, 0),
+            "WEBVTT\n\n00:00:03.000 --> 00:00:04.000\nKept\n\n"
+        );
+    }
+}
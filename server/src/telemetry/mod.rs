@@ -0,0 +1,193 @@
+//
+//  media-savant-api
+//  telemetry/mod.rs
+//
+
+mod request_id;
+
+use std::io::{self, Write};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tracing::{field, info_span, Span};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+pub use request_id::{current_request_id, trace_requests, REQUEST_ID_HEADER};
+
+use crate::config::{LogFormat, TelemetryConfig};
+use crate::redact::sanitize;
+
+/// Keeps the span exporter alive; call `shutdown` before exiting so queued spans are flushed.
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Flushes queued spans and buffered log output; call last before exiting.
+    pub fn shutdown(self) {
+        #[cfg(feature = "otlp")]
+        if let Some(Err(err)) = self.provider.map(|provider| provider.shutdown()) {
+            eprintln!("Failed to flush spans: {err}");
+        }
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Installs the global subscriber. `log` records from dependencies are forwarded into it, so
+/// they carry the request span like everything else.
+pub fn init(config: &TelemetryConfig) -> anyhow::Result<Telemetry> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (text, json) = match config.log_format {
+        LogFormat::Text => (Some(fmt::layer().with_writer(RedactingStdout)), None),
+        LogFormat::Json => (
+            None,
+            Some(
+                fmt::layer()
+                    .with_writer(RedactingStdout)
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(false),
+            ),
+        ),
+    };
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(text)
+        .with(json);
+
+    #[cfg(feature = "otlp")]
+    {
+        let (layer, provider) = match &config.otlp_endpoint {
+            Some(endpoint) => {
+                let (layer, provider) = otlp::layer(endpoint, &config.service_name)?;
+                (Some(layer), Some(provider))
+            }
+            None => (None, None),
+        };
+        registry.with(layer).try_init()?;
+        Ok(Telemetry { provider })
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        registry.try_init()?;
+        if config.otlp_endpoint.is_some() {
+            tracing::warn!(
+                "OTEL_EXPORTER_OTLP_ENDPOINT is set, but this build has no otlp feature"
+            );
+        }
+        Ok(Telemetry {})
+    }
+}
+
+/// Log output passes through `sanitize`, so an error that quotes a URL with `api_key` or a
+/// token is cleaned up even when the call site forgot to.
+struct RedactingStdout;
+
+impl<'a> MakeWriter<'a> for RedactingStdout {
+    type Writer = RedactedLine;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactedLine(Vec::new())
+    }
+}
+
+/// Buffers one formatted event and writes it out sanitized once complete.
+struct RedactedLine(Vec<u8>);
+
+impl Write for RedactedLine {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RedactedLine {
+    fn drop(&mut self) {
+        let line = sanitize(&String::from_utf8_lossy(&self.0));
+        let _ = io::stdout().lock().write_all(line.as_bytes());
+    }
+}
+
+/// Span for one call to Jellyfin; `status` is recorded once headers arrive.
+pub fn upstream_span(method: &str, path: &str) -> Span {
+    info_span!("jellyfin.request", method, path, status = field::Empty)
+}
+
+/// Carries the request ID, and with `otlp` the current trace context, to Jellyfin.
+pub fn propagate(headers: &mut HeaderMap) {
+    if let Some(id) = current_request_id().and_then(|id| HeaderValue::from_str(&id).ok()) {
+        headers.insert(HeaderName::from_static(REQUEST_ID_HEADER), id);
+    }
+
+    #[cfg(feature = "otlp")]
+    otlp::inject(headers);
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use std::collections::HashMap;
+
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+    use tracing::Subscriber;
+    use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+    use tracing_subscriber::registry::LookupSpan;
+
+    pub fn layer<S>(
+        endpoint: &str,
+        service_name: &str,
+    ) -> anyhow::Result<(
+        OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>,
+        SdkTracerProvider,
+    )>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(service_name.to_string())
+                    .build(),
+            )
+            .build();
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let tracer = provider.tracer("media-savant-api");
+        Ok((tracing_opentelemetry::layer().with_tracer(tracer), provider))
+    }
+
+    /// Writes `traceparent` for the current span so Jellyfin-side traces can join ours.
+    pub fn inject(headers: &mut HeaderMap) {
+        let context = tracing::Span::current().context();
+        let mut fields = HashMap::new();
+        opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut fields)
+        });
+        for (name, value) in fields {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+    }
+}
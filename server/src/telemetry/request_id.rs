@@ -0,0 +1,94 @@
+//
+//  media-savant-api
+//  telemetry/request_id.rs
+//
+
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use tracing::{field, info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+use crate::redact::sanitize;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest caller-supplied ID that is kept; anything longer is replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// ID of the request being handled on this task, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Assigns every request an ID, runs it inside a span carrying that ID and logs its outcome.
+///
+/// A well-formed `X-Request-Id` from the caller (e.g. a reverse proxy) is kept, so one ID
+/// follows the request from the edge through to Jellyfin. The ID is echoed on the response.
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|val| val.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = info_span!(
+        "http.request",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.path(),
+        route = field::Empty,
+        status = field::Empty,
+    );
+    if let Some(route) = req.match_pattern() {
+        span.record("route", route);
+    }
+
+    let started = Instant::now();
+    let result = REQUEST_ID
+        .scope(id.clone(), next.call(req))
+        .instrument(span.clone())
+        .await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(mut response) => {
+            let status = response.status().as_u16();
+            span.record("status", status);
+            span.in_scope(|| info!(status, elapsed_ms, "request completed"));
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            Ok(response)
+        }
+        Err(err) => {
+            span.in_scope(
+                || warn!(elapsed_ms, error = %sanitize(&err.to_string()), "request failed"),
+            );
+            Err(err)
+        }
+    }
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.'))
+}
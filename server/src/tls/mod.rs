@@ -0,0 +1,152 @@
+//
+//  media-savant-api
+//  tls/mod.rs
+//
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use anyhow::Context;
+use arc_swap::ArcSwap;
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+
+use crate::config::TlsConfig;
+use crate::state::AppState;
+
+/// Serves the most recently loaded certificate, so renewed certificates are picked up by new
+/// connections without a restart.
+pub struct CertResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    current: ArcSwap<CertifiedKey>,
+}
+
+impl CertResolver {
+    pub fn new(config: &TlsConfig) -> anyhow::Result<Self> {
+        let provider = Arc::new(ring::default_provider());
+        let current = load(&config.cert_path, &config.key_path, &provider)?;
+        Ok(Self {
+            cert_path: config.cert_path.clone(),
+            key_path: config.key_path.clone(),
+            provider,
+            current: ArcSwap::from_pointee(current),
+        })
+    }
+
+    /// Files the resolver reads, for watching.
+    pub fn paths(&self) -> [&Path; 2] {
+        [&self.cert_path, &self.key_path]
+    }
+
+    /// Re-reads the certificate and key; the current pair stays in use if they don't load.
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let next = load(&self.cert_path, &self.key_path, &self.provider)?;
+        self.current.store(Arc::new(next));
+        Ok(())
+    }
+
+    /// TLS settings for the HTTPS listeners; actix adds the ALPN entries for HTTP/2.
+    pub fn server_config(self: &Arc<Self>) -> anyhow::Result<rustls::ServerConfig> {
+        let config = rustls::ServerConfig::builder_with_provider(self.provider.clone())
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        Ok(config)
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.load_full())
+    }
+}
+
+impl fmt::Debug for CertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertResolver")
+            .field("cert_path", &self.cert_path)
+            .field("key_path", &self.key_path)
+            .finish_non_exhaustive()
+    }
+}
+
+fn load(
+    cert_path: &Path,
+    key_path: &Path,
+    provider: &CryptoProvider,
+) -> anyhow::Result<CertifiedKey> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read certificates from {}", cert_path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in {}", cert_path.display());
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("Failed to read private key from {}", key_path.display()))?;
+
+    CertifiedKey::from_der(certs, key, provider).with_context(|| {
+        format!(
+            "{} does not hold a usable key for {}",
+            key_path.display(),
+            cert_path.display()
+        )
+    })
+}
+
+/// Answers requests on the plain-HTTP redirect listener with a permanent redirect to HTTPS, on
+/// the port of the HTTPS listener sharing its address.
+pub async fn redirect_to_https(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let local = req.app_config().local_addr();
+    let https_port = req
+        .app_data::<web::Data<AppState>>()
+        .filter(|state| state.config().tls.is_some())
+        .and_then(|state| {
+            let config = state.config();
+            config
+                .server
+                .binds
+                .iter()
+                .find(|bind| bind.ip() == local.ip())
+                .or(config.server.binds.first())
+                .map(|bind| bind.port())
+        });
+    let Some(https_port) = https_port.filter(|_| !req.app_config().secure()) else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let connection = req.connection_info().clone();
+    let host = host_name(connection.host());
+    let authority = if https_port == 443 {
+        host.to_string()
+    } else {
+        format!("{host}:{https_port}")
+    };
+    let path = req.uri().path_and_query().map_or("/", |path| path.as_str());
+    let response = HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, format!("https://{authority}{path}")))
+        .finish();
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+/// `Host` without its port, keeping IPv6 literals bracketed.
+fn host_name(host: &str) -> &str {
+    if host.starts_with('[') {
+        host.split_inclusive(']').next().unwrap_or(host)
+    } else {
+        host.split(':').next().unwrap_or(host)
+    }
+}
@@ -0,0 +1,92 @@
+//
+//  media-savant-api
+//  wol/mod.rs
+//
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use tokio::net::UdpSocket;
+
+/// Where magic packets go when a server only names its MAC address.
+pub const DEFAULT_BROADCAST: SocketAddr =
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), DEFAULT_PORT);
+/// The discard port, which most network cards listen on for magic packets.
+pub const DEFAULT_PORT: u16 = 9;
+
+/// Parses `aa:bb:cc:dd:ee:ff`, also accepting `-` as the separator.
+pub fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let mut bytes = [0u8; 6];
+    let mut parts = mac.trim().split([':', '-']);
+    for byte in &mut bytes {
+        let part = parts.next()?;
+        // `from_str_radix` alone would also take a sign, as in `+a`.
+        if part.len() != 2 || !part.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+    parts.next().is_none().then_some(bytes)
+}
+
+/// Six `0xff` bytes followed by the MAC address sixteen times.
+pub fn magic_packet(mac: [u8; 6]) -> [u8; 102] {
+    let mut packet = [0xff; 102];
+    for chunk in packet[6..].chunks_exact_mut(6) {
+        chunk.copy_from_slice(&mac);
+    }
+    packet
+}
+
+/// Broadcasts a magic packet for `mac` to `target`.
+pub async fn send(mac: [u8; 6], target: SocketAddr) -> std::io::Result<()> {
+    let bind: SocketAddr = if target.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&magic_packet(mac), target).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_macs() {
+        let expected = Some([0xaa, 0xbb, 0xcc, 0x01, 0x23, 0xef]);
+        assert_eq!(parse_mac("aa:bb:cc:01:23:ef"), expected);
+        assert_eq!(parse_mac("AA-BB-CC-01-23-EF"), expected);
+        assert_eq!(parse_mac(" aa:bb:cc:01:23:ef\n"), expected);
+    }
+
+    #[test]
+    fn rejects_malformed_macs() {
+        for mac in [
+            "",
+            "aa:bb:cc:dd:ee",
+            "aa:bb:cc:dd:ee:ff:00",
+            "aa:bb:cc:dd:ee:f",
+            "aabb:cc:dd:ee:ff",
+            "aa:bb:cc:dd:ee:gg",
+            "+a:bb:cc:dd:ee:ff",
+            "aa:bb:cc:dd:ee:-f",
+            "aa bb cc dd ee ff",
+        ] {
+            assert_eq!(parse_mac(mac), None, "{mac:?}");
+        }
+    }
+
+    #[test]
+    fn builds_magic_packets() {
+        let mac = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+        let packet = magic_packet(mac);
+        assert_eq!(packet.len(), 102);
+        assert_eq!(packet[..6], [0xff; 6]);
+        for chunk in packet[6..].chunks(6) {
+            assert_eq!(chunk, mac);
+        }
+    }
+}
@@ -14,7 +14,7 @@ interface CastMemberProps {
 
 export function CastMember({ person, className }: CastMemberProps) {
   const imageUrl = person.Id && person.PrimaryImageTag
-    ? `/api/jellyfin/Items/${person.Id}/Images/Primary?fillWidth=280&quality=80`
+    ? `/api/images/${person.Id}/Primary?tag=${person.PrimaryImageTag}&fill_width=280&quality=80`
     : null
 
   return (
@@ -1,10 +1,10 @@
 import { Link } from '@tanstack/react-router'
 import { Play } from 'lucide-react'
-import { imageUrl } from '../../lib/jellyfin'
+import { imageUrl, type ItemImageTags } from '../../lib/jellyfin'
 import { cn } from '../../lib/utils'
 
 interface EpisodeCardProps {
-  item: {
+  item: ItemImageTags & {
     Id: string
     Name: string
     Overview?: string
@@ -37,7 +37,7 @@ export function EpisodeCard({ item, className }: EpisodeCardProps) {
       {/* Thumbnail */}
       <div className="relative aspect-video">
         <img
-          src={imageUrl(item.Id, 800)}
+          src={imageUrl(item.Id, 800, item.ImageTags?.Primary)}
           alt={item.Name}
           className="w-full h-full object-cover"
           loading="lazy"
@@ -3,10 +3,10 @@ import { Play, Info } from 'lucide-react'
 import { useState } from 'react'
 import { Button } from '../ui/Button'
 import { Badge } from '../ui/Badge'
-import { backdropUrl } from '../../lib/jellyfin'
+import { backdropTag, backdropUrl, type ItemImageTags } from '../../lib/jellyfin'
 
 interface HeroSectionProps {
-  item: {
+  item: ItemImageTags & {
     Id: string
     Name: string
     Overview?: string
@@ -40,13 +40,14 @@ export function HeroSection({ item }: HeroSectionProps) {
   // Fallback chain for backdrop
   const primaryBackdropId = getBackdropId()
   const fallbackBackdropId = item.ParentId || item.Id
+  const backdropId = backdropError ? fallbackBackdropId : primaryBackdropId
 
   return (
     <section className="relative h-[85vh] min-h-[600px] w-full overflow-hidden">
       {/* Backdrop Image */}
       <div className="absolute inset-0">
         <img
-          src={backdropUrl(backdropError ? fallbackBackdropId : primaryBackdropId)}
+          src={backdropUrl(backdropId, 1920, backdropTag(item, backdropId))}
           alt={item.Name}
           className="w-full h-full object-cover"
           onError={() => !backdropError && setBackdropError(true)}
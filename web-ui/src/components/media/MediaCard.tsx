@@ -1,11 +1,11 @@
 import { Link } from '@tanstack/react-router'
 import { Star } from 'lucide-react'
-import { imageUrl } from '../../lib/jellyfin'
+import { imageUrl, type ItemImageTags } from '../../lib/jellyfin'
 import { Badge } from '../ui/Badge'
 import { cn } from '../../lib/utils'
 
 interface MediaCardProps {
-  item: {
+  item: ItemImageTags & {
     Id: string
     Name: string
     Type?: string
@@ -31,7 +31,7 @@ export function MediaCard({ item, showRating = false, showGenres = false, classN
       {/* Poster */}
       <div className="relative aspect-[2/3] rounded-xl overflow-hidden bg-muted/40 border border-muted/20 group-hover:border-muted/60 transition-all duration-300">
         <img
-          src={imageUrl(item.Id, 400)}
+          src={imageUrl(item.Id, 400, item.ImageTags?.Primary)}
           alt={item.Name}
           className="w-full h-full object-cover transition-transform duration-300 group-hover:scale-105"
           loading="lazy"
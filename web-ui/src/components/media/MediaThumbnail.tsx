@@ -1,11 +1,11 @@
 import { Link } from '@tanstack/react-router'
 import { Play } from 'lucide-react'
-import { imageUrl } from '../../lib/jellyfin'
+import { imageUrl, placeholderUrl, type ImageBlurHashes, type ItemImageTags } from '../../lib/jellyfin'
 import { Badge } from '../ui/Badge'
 import { cn } from '../../lib/utils'
 
 interface MediaThumbnailProps {
-  item: {
+  item: ItemImageTags & {
     Id: string
     Name: string
     Type?: string
@@ -18,6 +18,7 @@ interface MediaThumbnailProps {
       PlaybackPositionTicks?: number
     }
     DateCreated?: string
+    ImageBlurHashes?: ImageBlurHashes
   }
   showProgress?: boolean
   showBadge?: boolean
@@ -34,6 +35,7 @@ export function MediaThumbnail({
 }: MediaThumbnailProps) {
   const progress = item.UserData?.PlayedPercentage || 0
   const isEpisode = item.Type === 'Episode'
+  const placeholder = placeholderUrl(item.Id, item.ImageBlurHashes)
   const episodeInfo = isEpisode
     ? `S${item.ParentIndexNumber} E${item.IndexNumber}`
     : null
@@ -57,9 +59,12 @@ export function MediaThumbnail({
       className={cn('group flex-shrink-0 w-[300px] space-y-2', className)}
     >
       {/* Thumbnail */}
-      <div className="relative aspect-video rounded-xl overflow-hidden bg-muted/40 border border-muted/20 group-hover:border-muted/60 transition-all duration-300">
+      <div
+        className="relative aspect-video rounded-xl overflow-hidden bg-muted/40 bg-cover bg-center border border-muted/20 group-hover:border-muted/60 transition-all duration-300"
+        style={placeholder ? { backgroundImage: `url(${placeholder})` } : undefined}
+      >
         <img
-          src={imageUrl(item.Id, 600)}
+          src={imageUrl(item.Id, 600, item.ImageTags?.Primary)}
           alt={item.Name}
           className="w-full h-full object-cover transition-transform duration-300 group-hover:scale-105"
           loading="lazy"
@@ -8,7 +8,15 @@ export type JellyfinPerson = {
   PrimaryImageTag?: string
 }
 
-export type JellyfinItem = {
+/** Jellyfin's image tags, which change whenever the artwork does. */
+export type ItemImageTags = {
+  ImageTags?: Record<string, string>
+  BackdropImageTags?: string[]
+  ParentBackdropItemId?: string
+  ParentBackdropImageTags?: string[]
+}
+
+export type JellyfinItem = ItemImageTags & {
   Id: string
   Name: string
   Type: string
@@ -141,16 +149,39 @@ export async function searchItems(userId: string, term: string) {
   return apiFetch<JellyfinItemsResponse>(`/jellyfin/Users/${userId}/Items?${params.toString()}`)
 }
 
-export function imageUrl(itemId: string, width = 320) {
-  return `/api/jellyfin/Items/${itemId}/Images/Primary?fillWidth=${width}&quality=80`
+/** Tagged artwork is cached for good; untagged URLs are revalidated after an hour. */
+function withTag(url: string, tag?: string) {
+  return tag ? `${url}&tag=${encodeURIComponent(tag)}` : url
 }
 
-export function backdropUrl(itemId: string, width = 1920) {
-  return `/api/jellyfin/Items/${itemId}/Images/Backdrop?fillWidth=${width}&quality=80`
+export function imageUrl(itemId: string, width = 320, tag?: string) {
+  return withTag(`/api/images/${itemId}/Primary?fill_width=${width}&quality=80`, tag)
 }
 
-export function thumbUrl(itemId: string, width = 480) {
-  return `/api/jellyfin/Items/${itemId}/Images/Thumb?fillWidth=${width}&quality=80`
+export function backdropUrl(itemId: string, width = 1920, tag?: string) {
+  return withTag(`/api/images/${itemId}/Backdrop?fill_width=${width}&quality=80`, tag)
+}
+
+export function thumbUrl(itemId: string, width = 480, tag?: string) {
+  return withTag(`/api/images/${itemId}/Thumb?fill_width=${width}&quality=80`, tag)
+}
+
+/** Tag of the backdrop shown for `backdropId`: the item's own, or the one it inherits. */
+export function backdropTag(item: ItemImageTags & { Id: string }, backdropId: string) {
+  if (backdropId === item.Id) return item.BackdropImageTags?.[0]
+  if (backdropId === item.ParentBackdropItemId) return item.ParentBackdropImageTags?.[0]
+  return undefined
+}
+
+export type ImageBlurHashes = Partial<Record<string, Record<string, string>>>
+
+/** Rendered BlurHash for an item's first image of `imageType`, if Jellyfin provided one. */
+export function placeholderUrl(itemId: string, blurHashes?: ImageBlurHashes, imageType = 'Primary') {
+  const hashes = blurHashes?.[imageType]
+  if (!hashes) return undefined
+  const [tag, hash] = Object.entries(hashes)[0] ?? []
+  if (!tag || !hash) return undefined
+  return `/api/images/${itemId}/${imageType}/placeholder?tag=${encodeURIComponent(tag)}&hash=${encodeURIComponent(hash)}`
 }
 
 export function streamUrl(itemId: string) {
@@ -9,6 +9,7 @@ import {
   fetchSeasons,
   fetchSimilar,
   imageUrl,
+  backdropTag,
   backdropUrl,
   streamUrl,
   type JellyfinItem,
@@ -128,7 +129,7 @@ function ItemDetail() {
             autoPlay
             className="w-full h-full max-h-screen"
             src={streamUrl(item.Id)}
-            poster={backdropUrl(item.Id)}
+            poster={backdropUrl(item.Id, 1920, backdropTag(item, item.Id))}
           />
         </div>
       )}
@@ -138,7 +139,7 @@ function ItemDetail() {
         {/* Backdrop */}
         <div className="absolute inset-0">
           <img
-            src={backdropUrl(getBackdropId())}
+            src={backdropUrl(getBackdropId(), 1920, backdropTag(item, getBackdropId()))}
             alt={item.Name}
             className="w-full h-full object-cover"
           />
@@ -152,7 +153,7 @@ function ItemDetail() {
             {/* Poster */}
             <div className="hidden lg:block flex-shrink-0 w-[280px] rounded-xl overflow-hidden border border-muted/40 shadow-2xl">
               <img
-                src={imageUrl(item.Id, 560)}
+                src={imageUrl(item.Id, 560, item.ImageTags?.Primary)}
                 alt={item.Name}
                 className="w-full h-auto"
               />